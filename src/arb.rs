@@ -0,0 +1,938 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::CharIndices;
+
+use serde_json::{Map, Value, json};
+
+use crate::collect::collect_and_build_modules_with;
+use crate::context::{Diagnostics, KeyDiagnostic};
+use crate::errors::WoofError;
+use crate::interpolations::{
+  CustomType, InterpolationType, ParsedInterpolation, parse_str, parse_str_with_types,
+};
+use crate::parse::{BuildOptions, Key, Locale, Message, Module, PLURAL_CATEGORIES, Part, Variant};
+use crate::sanitize::unescape_translation;
+use crate::update::Updater;
+
+/// Writes an `app_<locale>.arb` file per locale for Flutter's `gen-l10n`.
+///
+/// Key paths are flattened into camelCase keys, e.g. `about.more.copyright` becomes
+/// `aboutMoreCopyright`. Every message has `@key` metadata with the types of its placeholders,
+/// and its comment as description. Selects on numbers become ICU plurals.
+pub fn export(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let keys = arb_keys(module);
+  for locale in locales {
+    let filename = format!("app_{}.arb", arb_locale(locale));
+    fs::write(dir.join(filename), write_arb(locale, &keys))?;
+  }
+
+  Ok(())
+}
+
+/// Imports translated ARB files into the TOML files of the input directory.
+///
+/// Keys are matched against the camelCase key paths of the current messages, and placeholders
+/// get the types they have in the current messages. Messages that are unchanged, use unsupported
+/// ICU syntax or don't exist are skipped, and the latter two are reported as diagnostics.
+/// Returns the updated files and the number of changed translations in each.
+pub fn import(
+  input_dir: &Path,
+  files: &[impl AsRef<Path>],
+  options: &BuildOptions,
+) -> Result<(Vec<(PathBuf, usize)>, Diagnostics), WoofError> {
+  let current = collect_and_build_modules_with(input_dir, options)?;
+  let keys = arb_keys(&current.module)
+    .into_iter()
+    .map(|(key, key_path, message)| (key, (key_path, message)))
+    .collect::<HashMap<_, _>>();
+
+  let mut updater = Updater::new(input_dir)?;
+  let mut diagnostics = Diagnostics::default();
+
+  for file in files {
+    let file = file.as_ref();
+    let filename = file.display().to_string();
+    let contents = fs::read_to_string(file)?;
+
+    let (locale, changes) = read_arb(
+      &filename,
+      &contents,
+      &keys,
+      &current.locales,
+      &options.custom_types,
+      &mut diagnostics,
+    )?;
+
+    for (key_path, translation) in changes {
+      updater.set_dotted(&locale, &key_path, &translation)?;
+    }
+  }
+
+  Ok((updater.save()?, diagnostics))
+}
+
+/// Assigns every message a camelCase key. Keys that end up the same get a number appended.
+fn arb_keys(module: &Module) -> Vec<(String, String, &Message)> {
+  let mut used = HashSet::new();
+
+  module
+    .flatten_dotted()
+    .into_iter()
+    .map(|(key_path, message)| {
+      let base = camel_case(&key_path);
+      let mut key = base.clone();
+      let mut n = 2;
+
+      while used.contains(&key) {
+        key = format!("{base}{n}");
+        n += 1;
+      }
+
+      used.insert(key.clone());
+      (key, key_path, message)
+    })
+    .collect()
+}
+
+/// Converts a key path like `nav.home-page` into a key like `navHomePage`. Keys have to be valid
+/// Dart identifiers, so other characters are removed.
+fn camel_case(key_path: &str) -> String {
+  let mut key = String::new();
+
+  for word in key_path
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .filter(|word| !word.is_empty())
+  {
+    let mut chars = word.chars();
+    let first = chars.next().expect("words aren't empty");
+
+    if key.is_empty() {
+      key.push(first.to_ascii_lowercase());
+    } else {
+      key.push(first.to_ascii_uppercase());
+    }
+
+    key.push_str(chars.as_str());
+  }
+
+  if !key.starts_with(|c: char| c.is_ascii_lowercase()) {
+    key.insert_str(0, "key");
+  }
+
+  key
+}
+
+/// Flutter uses underscores to separate the subtags of locales
+fn arb_locale(locale: &Locale) -> String {
+  locale.0.replace('-', "_")
+}
+
+fn write_arb(locale: &Locale, keys: &[(String, String, &Message)]) -> String {
+  let mut arb = Map::new();
+  arb.insert("@@locale".to_string(), json!(arb_locale(locale)));
+
+  for (key, _, message) in keys {
+    let Some(parts) = message.parts_for_locale(locale) else {
+      continue;
+    };
+
+    arb.insert(key.clone(), json!(write_icu(message, &parts)));
+
+    let mut metadata = Map::new();
+    if let Some(comment) = message
+      .metadata
+      .description
+      .as_ref()
+      .or_else(|| message.comments.get(locale))
+    {
+      metadata.insert("description".to_string(), json!(comment));
+    }
+
+    // Other metadata is written as custom attributes, which Flutter ignores
+    if let Some(max_length) = message.metadata.max_length {
+      metadata.insert("x-maxLength".to_string(), json!(max_length));
+    }
+
+    if let Some(reason) = &message.metadata.deprecated {
+      metadata.insert("x-deprecated".to_string(), json!(reason));
+    }
+
+    if !message.metadata.tags.is_empty() {
+      metadata.insert("x-tags".to_string(), json!(message.metadata.tags));
+    }
+
+    if !message.interpolations.is_empty() {
+      let placeholders = message
+        .interpolations
+        .iter()
+        .map(|(key, interpolation)| {
+          // Selects in ARB only work on strings, so booleans and enums are passed as strings
+          let type_ = match interpolation.type_ {
+            InterpolationType::Number => "num",
+            InterpolationType::None
+            | InterpolationType::String
+            | InterpolationType::Boolean
+            | InterpolationType::Enum(_)
+            | InterpolationType::Custom(_) => "String",
+          };
+
+          (key.literal.clone(), json!({ "type": type_ }))
+        })
+        .collect::<Map<_, _>>();
+
+      metadata.insert("placeholders".to_string(), Value::Object(placeholders));
+    }
+
+    if !metadata.is_empty() {
+      arb.insert(format!("@{key}"), Value::Object(metadata));
+    }
+  }
+
+  let mut out = serde_json::to_string_pretty(&arb).expect("ARB files only contain strings");
+  out.push('\n');
+  out
+}
+
+/// Renders parts as an ICU message. Selects on numbers become plurals, and the default variant
+/// becomes `other`, which ICU requires.
+fn write_icu(message: &Message, parts: &[Part]) -> String {
+  let mut out = String::new();
+
+  for part in parts {
+    match part {
+      Part::Text(text) => {
+        for c in unescape_translation(text).chars() {
+          match c {
+            '{' | '}' => {
+              out.push('\'');
+              out.push(c);
+              out.push('\'');
+            }
+            c => out.push(c),
+          }
+        }
+      }
+      Part::Interpolation { name: key, .. } => {
+        out.push('{');
+        out.push_str(&key.literal);
+        out.push('}');
+      }
+      Part::Select { name, variants } => write_select(&mut out, message, name, variants),
+    }
+  }
+
+  out
+}
+
+fn write_select(out: &mut String, message: &Message, name: &Key, variants: &[Variant]) {
+  let is_number = message
+    .interpolations
+    .get(name)
+    .is_some_and(|i| i.type_ == InterpolationType::Number);
+
+  out.push('{');
+  out.push_str(&name.literal);
+  out.push_str(if is_number { ", plural," } else { ", select," });
+
+  for variant in variants {
+    let key = if variant.default {
+      "other".to_string()
+    } else if variant.key == "other" {
+      // The default variant takes the place of `other`
+      continue;
+    } else if !is_number {
+      variant.key.clone()
+    } else if let Ok(number) = variant.key.parse::<f64>() {
+      format!("={number}")
+    } else if PLURAL_CATEGORIES.contains(&variant.key.as_str()) {
+      variant.key.clone()
+    } else {
+      // Numbers can never match other keys
+      continue;
+    };
+
+    out.push(' ');
+    out.push_str(&key);
+    out.push('{');
+    out.push_str(&write_icu(message, &variant.parts));
+    out.push('}');
+  }
+
+  out.push('}');
+}
+
+/// Reads the messages of an ARB file, returning its locale and the key path and translation of
+/// every changed message
+fn read_arb(
+  filename: &str,
+  contents: &str,
+  keys: &HashMap<String, (String, &Message)>,
+  locales: &[Locale],
+  custom_types: &[CustomType],
+  diagnostics: &mut Diagnostics,
+) -> Result<(Locale, Vec<(String, String)>), WoofError> {
+  let invalid = |reason: String| WoofError::InvalidArbFile(filename.to_string(), reason);
+
+  let arb = serde_json::from_str::<Value>(contents).map_err(|err| invalid(err.to_string()))?;
+  let Some(arb) = arb.as_object() else {
+    return Err(invalid("Expected an object".to_string()));
+  };
+
+  let Some(arb_locale) = arb.get("@@locale").and_then(Value::as_str) else {
+    return Err(invalid("Missing @@locale".to_string()));
+  };
+
+  // Locales are matched regardless of the subtag separator
+  let locale = locales
+    .iter()
+    .find(|locale| locale.0.replace('-', "_") == arb_locale.replace('-', "_"))
+    .cloned()
+    .unwrap_or_else(|| Locale(arb_locale.replace('_', "-")));
+
+  let mut changes = Vec::new();
+
+  for (key, value) in arb {
+    if key.starts_with('@') {
+      continue;
+    }
+
+    let Some(value) = value.as_str().filter(|value| !value.is_empty()) else {
+      continue;
+    };
+
+    let Some((key_path, message)) = keys.get(key) else {
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        filename,
+        key.clone(),
+        KeyDiagnostic::UnknownArbKey,
+      );
+
+      continue;
+    };
+
+    let unchanged = message
+      .parts_for_locale(&locale)
+      .is_some_and(|parts| write_icu(message, &parts) == value);
+
+    if unchanged {
+      continue;
+    }
+
+    // Exports contain the referenced text, importing it would replace the references
+    let has_references = message
+      .translation
+      .get(&locale)
+      .is_some_and(|translation| !parse_str(&translation.0).references.is_empty());
+
+    if has_references {
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        filename,
+        key_path.clone(),
+        KeyDiagnostic::ArbReferences,
+      );
+
+      continue;
+    }
+
+    // Placeholders that are new to the message get the type from the ARB metadata
+    let metadata_types = arb
+      .get(&format!("@{key}"))
+      .and_then(|metadata| metadata.get("placeholders"))
+      .and_then(Value::as_object)
+      .map(|placeholders| {
+        placeholders
+          .iter()
+          .filter_map(|(name, placeholder)| {
+            let type_ = placeholder.get("type")?.as_str()?;
+            let type_ = match type_ {
+              "num" | "int" | "double" => InterpolationType::Number,
+              _ => InterpolationType::None,
+            };
+
+            Some((name.clone(), type_))
+          })
+          .collect::<HashMap<_, _>>()
+      })
+      .unwrap_or_default();
+
+    let parser = IcuParser {
+      chars: value.char_indices().peekable(),
+      len: value.len(),
+      message,
+      metadata_types: &metadata_types,
+      defaults: &defaults(message, &locale, custom_types),
+    };
+
+    let translation = match parser.parse() {
+      Ok(translation) => translation,
+      Err((offset, reason)) => {
+        diagnostics.add_key_diagnostics(
+          locale.clone(),
+          filename,
+          key_path.clone(),
+          KeyDiagnostic::UnsupportedIcuSyntax {
+            source_code: value.to_string(),
+            span: (offset, 0).into(),
+            reason,
+          },
+        );
+
+        continue;
+      }
+    };
+
+    let parsed = parse_str_with_types(&translation, custom_types);
+    if !parsed.errors.is_empty() {
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        filename,
+        key_path.clone(),
+        KeyDiagnostic::InterpolationErrors {
+          source_code: translation,
+          errors: parsed.errors,
+        },
+      );
+
+      continue;
+    }
+
+    changes.push((key_path.clone(), translation));
+  }
+
+  Ok((locale, changes))
+}
+
+/// Defaults of the interpolations in the current translation of a message, which ICU has no
+/// syntax for. Arguments that every translation can omit are kept optional in new translations,
+/// without a default.
+fn defaults(
+  message: &Message,
+  locale: &Locale,
+  custom_types: &[CustomType],
+) -> HashMap<String, String> {
+  fn collect(interpolations: &[ParsedInterpolation], out: &mut HashMap<String, String>) {
+    for interpolation in interpolations {
+      if let Some(default) = &interpolation.default {
+        out
+          .entry(interpolation.name.clone())
+          .or_insert_with(|| default.clone());
+      }
+
+      for variant in &interpolation.variants {
+        collect(&variant.interpolations, out);
+      }
+    }
+  }
+
+  let mut defaults = HashMap::new();
+  if let Some(translation) = message.translation.get(locale) {
+    let parsed = parse_str_with_types(&translation.0, custom_types);
+    collect(&parsed.interpolations, &mut defaults);
+  }
+
+  for (name, interpolation) in &message.interpolations {
+    if !interpolation.required {
+      defaults.entry(name.literal.clone()).or_default();
+    }
+  }
+
+  defaults
+}
+
+/// Converts ICU messages into translations, supporting placeholders, plurals and selects
+struct IcuParser<'a> {
+  chars: Peekable<CharIndices<'a>>,
+  len: usize,
+  message: &'a Message,
+  metadata_types: &'a HashMap<String, InterpolationType>,
+  /// Defaults written after placeholders of the same name, e.g. `{name=there}` or `{name?}`
+  defaults: &'a HashMap<String, String>,
+}
+
+type IcuResult<T> = Result<T, (usize, String)>;
+
+impl IcuParser<'_> {
+  fn parse(mut self) -> IcuResult<String> {
+    let mut out = String::new();
+    self.parse_message(&mut out, false)?;
+    Ok(out)
+  }
+
+  /// Parses text and arguments until the end, or until the closing brace of a variant
+  fn parse_message(&mut self, out: &mut String, in_variant: bool) -> IcuResult<()> {
+    while let Some(&(_, c)) = self.chars.peek() {
+      match c {
+        '}' if in_variant => return Ok(()),
+        '{' => {
+          self.chars.next();
+          self.parse_argument(out)?;
+        }
+        '\'' => {
+          self.chars.next();
+          self.parse_quoted(out, in_variant)?;
+        }
+        '}' => {
+          self.chars.next();
+          out.push('}');
+        }
+        c => {
+          self.chars.next();
+          out.push(c);
+        }
+      }
+    }
+
+    if in_variant {
+      return Err((self.len, "Unclosed variant".to_string()));
+    }
+
+    Ok(())
+  }
+
+  /// Parses the text after an apostrophe. Two apostrophes are a literal one, and an apostrophe
+  /// before a brace quotes everything up to the next apostrophe.
+  fn parse_quoted(&mut self, out: &mut String, in_variant: bool) -> IcuResult<()> {
+    match self.chars.peek() {
+      Some((_, '\'')) => {
+        self.chars.next();
+        out.push('\'');
+      }
+      Some((_, '{' | '}')) => {
+        for (offset, c) in self.chars.by_ref() {
+          match c {
+            '\'' => break,
+            '{' => out.push_str("{{"),
+            '}' if in_variant => {
+              return Err((
+                offset,
+                "Closing braces can't be used in plural or select variants".to_string(),
+              ));
+            }
+            c => out.push(c),
+          }
+        }
+      }
+      _ => out.push('\''),
+    }
+
+    Ok(())
+  }
+
+  /// Parses an argument after its opening brace
+  fn parse_argument(&mut self, out: &mut String) -> IcuResult<()> {
+    self.skip_whitespace();
+    let (offset, name) = self.word();
+    if name.is_empty() {
+      return Err((offset, "Expected a placeholder name".to_string()));
+    }
+
+    self.skip_whitespace();
+    match self.chars.next() {
+      Some((_, '}')) => {
+        out.push('{');
+        out.push_str(&name);
+        out.push_str(&self.annotation(&name, InterpolationType::None));
+        match self.defaults.get(&name).map(String::as_str) {
+          Some("") => out.push('?'),
+          Some(default) => {
+            out.push('=');
+            out.push_str(default);
+          }
+          None => {}
+        }
+        out.push('}');
+        return Ok(());
+      }
+      Some((_, ',')) => {}
+      Some((offset, _)) => return Err((offset, "Expected , or }".to_string())),
+      None => return Err((self.len, "Unclosed placeholder".to_string())),
+    }
+
+    self.skip_whitespace();
+    let (offset, kind) = self.word();
+    let is_plural = match kind.as_str() {
+      "plural" => true,
+      "select" => false,
+      kind => return Err((offset, format!("Unsupported argument type {kind}"))),
+    };
+
+    self.skip_whitespace();
+    if !matches!(self.chars.next(), Some((_, ','))) {
+      return Err((offset, format!("Expected , after {kind}")));
+    }
+
+    let fallback = if is_plural {
+      InterpolationType::Number
+    } else {
+      InterpolationType::None
+    };
+
+    out.push('{');
+    out.push_str(&name);
+    out.push_str(&self.annotation(&name, fallback));
+    out.push_str(" ->");
+
+    let mut has_other = false;
+    loop {
+      self.skip_whitespace();
+      let (offset, selector) = match self.chars.peek() {
+        Some((_, '}')) => {
+          self.chars.next();
+          break;
+        }
+        Some(_) => self.word(),
+        None => return Err((self.len, "Unclosed placeholder".to_string())),
+      };
+
+      let key = match selector.strip_prefix('=') {
+        Some(number) if is_plural && number.parse::<f64>().is_ok() => number.to_string(),
+        _ if selector.is_empty() || selector.contains(':') => {
+          return Err((offset, format!("Unsupported selector {selector}")));
+        }
+        _ => selector.clone(),
+      };
+
+      self.skip_whitespace();
+      if !matches!(self.chars.next(), Some((_, '{'))) {
+        return Err((offset, format!("Expected {{ after {selector}")));
+      }
+
+      out.push(' ');
+      if key == "other" {
+        has_other = true;
+        out.push('*');
+      }
+      out.push_str(&key);
+      out.push_str(" {");
+      self.parse_message(out, true)?;
+      self.chars.next();
+      out.push('}');
+    }
+
+    if !has_other {
+      return Err((offset, "Missing other variant".to_string()));
+    }
+
+    out.push('}');
+    Ok(())
+  }
+
+  /// Returns the type annotation for a placeholder, preferring the type it has in the current
+  /// message over the type from the ARB metadata
+  fn annotation(&self, name: &str, fallback: InterpolationType) -> String {
+    let type_ = self
+      .message
+      .interpolations
+      .get(&Key::new(name))
+      .map(|interpolation| interpolation.type_.clone())
+      .or_else(|| self.metadata_types.get(name).cloned())
+      .unwrap_or(fallback);
+
+    match type_ {
+      InterpolationType::None => String::new(),
+      type_ => format!(":{type_}"),
+    }
+  }
+
+  /// Reads characters up to the next whitespace, comma or brace
+  fn word(&mut self) -> (usize, String) {
+    let offset = self.chars.peek().map_or(self.len, |(offset, _)| *offset);
+    let mut word = String::new();
+
+    while let Some(&(_, c)) = self.chars.peek() {
+      if c.is_whitespace() || matches!(c, ',' | '{' | '}') {
+        break;
+      }
+
+      word.push(c);
+      self.chars.next();
+    }
+
+    (offset, word)
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::{module, module_with};
+  use std::collections::BTreeMap;
+
+  fn test_module() -> Module {
+    module(&[
+      (
+        "en",
+        r#"
+# Shown on the start page
+greeting = "Hello, {name}! {{Braces} and 'quotes'"
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *neutral {They}} replied"
+
+[nav]
+home-page = "Home"
+home_page = "Also home"
+"#,
+      ),
+      (
+        "pt-BR",
+        r#"
+greeting = "Olá, {name}!"
+items = "{count:number -> one {Um item} *other {{count} itens}}"
+"#,
+      ),
+    ])
+  }
+
+  #[test]
+  fn export_arb() {
+    let module = test_module();
+    let keys = arb_keys(&module);
+
+    insta::assert_snapshot!(format!(
+      "{}---\n{}",
+      write_arb(&Locale("en".to_string()), &keys),
+      write_arb(&Locale("pt-BR".to_string()), &keys)
+    ));
+  }
+
+  #[test]
+  fn import_unchanged_arb() {
+    let module = test_module();
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    let contents = write_arb(&locales[1], &arb_keys(&module));
+
+    let mut diagnostics = Diagnostics::default();
+    let (locale, changes) =
+      read_arb("pt.arb", &contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+
+    assert_eq!(locale, locales[1]);
+    assert!(changes.is_empty());
+    assert!(diagnostics.is_empty());
+  }
+
+  #[test]
+  fn import_changed_arb() {
+    let module = test_module();
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    let contents = r#"{
+  "@@locale": "pt_BR",
+  "greeting": "Olá, {name}! '{'Chaves'}' e ''aspas''",
+  "items": "{count, plural, =0{Nenhum item} one{Um item} other{{count} itens}}",
+  "reply": "{gender, select, female{Ela} other{Eles}} responderam",
+  "navHomePage": "Início",
+  "navHomePage2": "",
+  "unknownKey": "Desconhecido",
+  "@unknownKey": {}
+}"#;
+
+    let mut diagnostics = Diagnostics::default();
+    let (locale, changes) =
+      read_arb("pt.arb", contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+
+    assert_eq!(locale, locales[1]);
+    assert_eq!(diagnostics.file_diagnostics.len(), 1);
+    insta::assert_debug_snapshot!(changes);
+  }
+
+  #[test]
+  fn import_defaults_arb() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      ("de", r#"greeting = "Hallo {name=du}{suffix?}""#),
+    ]);
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let locales = [Locale("en".to_string()), Locale("de".to_string())];
+    let contents = write_arb(&locales[1], &arb_keys(&module));
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, changes) =
+      read_arb("de.arb", &contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+    assert!(changes.is_empty());
+
+    let contents = r#"{
+  "@@locale": "de",
+  "greeting": "Servus {name}{suffix}!",
+  "signature": "Grüße, {sender}"
+}"#;
+
+    let (_, changes) =
+      read_arb("de.arb", contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+      changes,
+      [
+        (
+          "greeting".to_string(),
+          "Servus {name=du}{suffix?}!".to_string()
+        ),
+        ("signature".to_string(), "Grüße, {sender?}".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn import_unsupported_icu() {
+    let module = test_module();
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    let contents = r#"{
+  "@@locale": "pt_BR",
+  "greeting": "Olá, {name, number}!",
+  "items": "{count, plural, one{Um item}}",
+  "reply": "{gender, select, female{'}'} other{Eles}}"
+}"#;
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, changes) =
+      read_arb("pt.arb", contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+    assert!(changes.is_empty());
+
+    // Diagnostics are stored in a hash map, so they're sorted for a stable snapshot
+    let diagnostics = diagnostics
+      .file_diagnostics
+      .into_values()
+      .flatten()
+      .flat_map(|(key, diagnostics)| {
+        diagnostics
+          .into_iter()
+          .map(move |diagnostic| (key.clone(), format!("{diagnostic:?}")))
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    insta::assert_debug_snapshot!(diagnostics);
+  }
+
+  #[test]
+  fn import_custom_types_arb() {
+    let money = CustomType {
+      name: "money".to_string(),
+      type_name: "Money".to_string(),
+      formatter: "formatMoney".to_string(),
+      module: "./money".to_string(),
+    };
+
+    let options = BuildOptions {
+      custom_types: vec![money],
+      ..Default::default()
+    };
+
+    let module = module_with(&[("en", r#"total = "Total: {total:money}""#)], &options);
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let contents = r#"{ "@@locale": "pt_BR", "total": "Soma: {total}" }"#;
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, changes) = read_arb(
+      "pt.arb",
+      contents,
+      &keys,
+      &[],
+      &options.custom_types,
+      &mut diagnostics,
+    )
+    .unwrap();
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+      changes,
+      [("total".to_string(), "Soma: {total:money}".to_string())]
+    );
+
+    // Without the custom type, `money` is an unknown type
+    let (_, changes) = read_arb("pt.arb", contents, &keys, &[], &[], &mut diagnostics).unwrap();
+
+    assert!(changes.is_empty());
+    assert_eq!(diagnostics.file_diagnostics.len(), 1);
+  }
+
+  #[test]
+  fn import_references_arb() {
+    let module = module(&[
+      ("en", "brand = \"Woof\"\nwelcome = \"Welcome to {@brand}\""),
+      (
+        "pt-BR",
+        "brand = \"Au\"\nwelcome = \"Bem-vindo ao {@brand}\"",
+      ),
+    ]);
+    let keys = arb_keys(&module)
+      .into_iter()
+      .map(|(key, key_path, message)| (key, (key_path, message)))
+      .collect::<HashMap<_, _>>();
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    let contents = write_arb(&locales[1], &arb_keys(&module));
+    assert!(contents.contains(r#""welcome": "Bem-vindo ao Au""#));
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, changes) =
+      read_arb("pt.arb", &contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+
+    assert!(changes.is_empty());
+    assert!(diagnostics.is_empty());
+
+    let contents = contents.replace("Bem-vindo ao Au", "Boas-vindas ao Au");
+    let (_, changes) =
+      read_arb("pt.arb", &contents, &keys, &locales, &[], &mut diagnostics).unwrap();
+
+    assert!(changes.is_empty());
+    assert!(matches!(
+      diagnostics.file_diagnostics.values().next().unwrap()["welcome"][..],
+      [KeyDiagnostic::ArbReferences]
+    ));
+  }
+
+  #[test]
+  fn invalid_arb() {
+    let mut diagnostics = Diagnostics::default();
+    let keys = HashMap::new();
+
+    for contents in ["not json", "[]", "{}"] {
+      let result = read_arb("pt.arb", contents, &keys, &[], &[], &mut diagnostics);
+      assert!(matches!(result, Err(WoofError::InvalidArbFile(..))));
+    }
+  }
+}
@@ -1,15 +1,19 @@
-use crate::context::Diagnostics;
+use crate::context::{Diagnostics, KeyDiagnostic};
 use crate::errors::WoofError;
-use crate::parse::{Locale, Module, build_flat_module, build_namespaced_module};
+use crate::fluent::parse_fluent;
+use crate::parse::{BuildOptions, Locale, Module, build_flat_module, build_namespaced_module};
 use crate::sanitize::is_valid_identifier;
 use std::collections::HashMap;
 use std::path::Path;
 use std::{env, fs};
-use toml::Value;
+use toml_edit::{DocumentMut, Table};
 
+/// How translation files in a directory are named
 #[derive(Debug, PartialEq)]
 pub enum FileMode {
+  /// One file per locale, e.g. `en.toml`
   Flat,
+  /// One file per namespace and locale, e.g. `common.en.toml`
   Namespaced,
 }
 
@@ -18,27 +22,31 @@ pub struct ParsedFile {
   /// The path of the file, either relative to the current working directory or (if outside of it)
   /// relative to the input directory
   pub normalized_path: String,
-  pub contents: Value,
+  pub contents: Table,
+  /// Diagnostics found while reading the file, keyed by their key path
+  pub diagnostics: Vec<(String, KeyDiagnostic)>,
 }
 
-#[derive(Debug)]
-pub struct NamespacedFile {
-  pub namespace: String,
-  pub file: ParsedFile,
+/// Checks whether a path points to a supported translation file
+fn is_translation_file(path: &Path) -> bool {
+  matches!(
+    path.extension().and_then(|s| s.to_str()),
+    Some("toml" | "ftl")
+  )
 }
 
 /// Determines the file mode by examining the files in the directory
-fn detect_file_mode(dir: &Path) -> Result<FileMode, WoofError> {
+pub fn detect_file_mode(dir: &Path) -> Result<FileMode, WoofError> {
   let entries = fs::read_dir(dir)?;
-  let toml_files = entries
+  let translation_files = entries
     .filter_map(|e| e.ok())
-    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("toml"))
+    .filter(|e| is_translation_file(&e.path()))
     .collect::<Vec<_>>();
 
   let mut has_flat = false;
   let mut has_namespaced = false;
 
-  for entry in toml_files {
+  for entry in translation_files {
     let path = entry.path();
     let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
       continue;
@@ -63,6 +71,37 @@ fn detect_file_mode(dir: &Path) -> Result<FileMode, WoofError> {
   }
 }
 
+/// Reads and parses a single translation file, based on its extension
+fn read_file(path: &Path, normalized_path: String) -> Result<ParsedFile, WoofError> {
+  let filename = path
+    .file_name()
+    .map(|s| s.to_string_lossy())
+    .unwrap_or_default()
+    .to_string();
+
+  let contents = fs::read_to_string(path)?;
+
+  if path.extension().and_then(|s| s.to_str()) == Some("ftl") {
+    let file = parse_fluent(&contents).map_err(|err| WoofError::Fluent(filename, err))?;
+
+    return Ok(ParsedFile {
+      normalized_path,
+      contents: file.contents,
+      diagnostics: file.diagnostics,
+    });
+  }
+
+  let document = contents
+    .parse::<DocumentMut>()
+    .map_err(|err| WoofError::Toml(filename, err))?;
+
+  Ok(ParsedFile {
+    normalized_path,
+    contents: document.as_table().clone(),
+    diagnostics: Vec::new(),
+  })
+}
+
 /// Collects locale files from a directory (flat mode)
 fn collect_flat(input_dir: &Path) -> Result<HashMap<Locale, ParsedFile>, WoofError> {
   let cwd = env::current_dir().map_err(WoofError::InvalidCwd)?;
@@ -75,11 +114,11 @@ fn collect_flat(input_dir: &Path) -> Result<HashMap<Locale, ParsedFile>, WoofErr
   }
 
   let entries = fs::read_dir(input_dir)?;
-  let toml_files = entries
+  let translation_files = entries
     .filter_map(|e| e.ok())
-    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("toml"));
+    .filter(|e| is_translation_file(&e.path()));
 
-  for entry in toml_files {
+  for entry in translation_files {
     let path = entry.path();
     let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
       // TODO: Log this
@@ -89,30 +128,20 @@ fn collect_flat(input_dir: &Path) -> Result<HashMap<Locale, ParsedFile>, WoofErr
     let normalized_path = normalize_path(&path, &cwd, input_dir);
 
     let locale = Locale(stem.to_string());
-    let contents = fs::read_to_string(&path)?;
-    let contents = toml::from_str(&contents).map_err(|err| {
-      let filename = path
-        .file_name()
-        .map(|s| s.to_string_lossy())
-        .unwrap_or_default()
-        .to_string();
-
-      WoofError::Toml(filename, err)
-    })?;
-
-    let file = ParsedFile {
-      normalized_path,
-      contents,
-    };
+    let file = read_file(&path, normalized_path)?;
 
-    result.insert(locale, file);
+    if result.insert(locale, file).is_some() {
+      return Err(WoofError::DuplicateFiles(format!("locale {stem}")));
+    }
   }
 
   Ok(result)
 }
 
 /// Collects namespaced files from a directory
-fn collect_namespaced(input_dir: &Path) -> Result<HashMap<Locale, NamespacedFile>, WoofError> {
+fn collect_namespaced(
+  input_dir: &Path,
+) -> Result<HashMap<(String, Locale), ParsedFile>, WoofError> {
   let cwd = env::current_dir().map_err(WoofError::InvalidCwd)?;
   let mut result = HashMap::new();
 
@@ -123,11 +152,11 @@ fn collect_namespaced(input_dir: &Path) -> Result<HashMap<Locale, NamespacedFile
   }
 
   let entries = fs::read_dir(input_dir)?;
-  let toml_files = entries
+  let translation_files = entries
     .filter_map(|e| e.ok())
-    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("toml"));
+    .filter(|e| is_translation_file(&e.path()));
 
-  for entry in toml_files {
+  for entry in translation_files {
     let path = entry.path();
     let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
       // TODO: Log this
@@ -150,44 +179,47 @@ fn collect_namespaced(input_dir: &Path) -> Result<HashMap<Locale, NamespacedFile
 
     let namespace = parts[0].to_string();
     let locale = Locale(parts[1].to_string());
+    let file = read_file(&path, normalized_path)?;
 
-    let contents = fs::read_to_string(&path)?;
-    let contents: Value = toml::from_str(&contents).map_err(|err| {
-      let filename = path
-        .file_name()
-        .map(|s| s.to_string_lossy())
-        .unwrap_or_default()
-        .to_string();
-      WoofError::Toml(filename, err)
-    })?;
-
-    let file = ParsedFile {
-      normalized_path,
-      contents,
-    };
-
-    result.insert(locale, NamespacedFile { namespace, file });
+    if result.insert((namespace, locale), file).is_some() {
+      return Err(WoofError::DuplicateFiles(format!("namespace {stem}")));
+    }
   }
 
   Ok(result)
 }
 
+/// The messages collected from a directory of translation files
 pub struct ModuleBuildResult {
   pub module: Module,
   pub diagnostics: Diagnostics,
+  /// All locales, sorted by name
   pub locales: Vec<Locale>,
 }
 
-/// Collects and builds modules from translation files, supporting both flat and namespaced modes
-pub fn collect_and_build_modules(dir: &str) -> Result<ModuleBuildResult, WoofError> {
-  let dir = Path::new(dir);
+/// Collects and builds modules from translation files, supporting both flat and namespaced modes.
+///
+/// Problems within the files are returned as diagnostics, only unreadable or invalid files cause
+/// an error.
+pub fn collect_and_build_modules(dir: impl AsRef<Path>) -> Result<ModuleBuildResult, WoofError> {
+  collect_and_build_modules_with(dir, &BuildOptions::default())
+}
+
+/// Collects and builds modules like [`collect_and_build_modules`], with length limits and custom
+/// interpolation types
+pub fn collect_and_build_modules_with(
+  dir: impl AsRef<Path>,
+  options: &BuildOptions,
+) -> Result<ModuleBuildResult, WoofError> {
+  let dir = dir.as_ref();
   let mode = detect_file_mode(dir)?;
 
   match mode {
     FileMode::Flat => {
       let files = collect_flat(dir)?;
-      let locales = files.keys().cloned().collect::<Vec<_>>();
-      let (module, diagnostics) = build_flat_module(files)?;
+      let mut locales = files.keys().cloned().collect::<Vec<_>>();
+      locales.sort();
+      let (module, diagnostics) = build_flat_module(files, options)?;
 
       Ok(ModuleBuildResult {
         module,
@@ -197,17 +229,24 @@ pub fn collect_and_build_modules(dir: &str) -> Result<ModuleBuildResult, WoofErr
     }
     FileMode::Namespaced => {
       let files = collect_namespaced(dir)?;
-      let locales = files.keys().cloned().collect::<Vec<_>>();
+      let mut locales = files
+        .keys()
+        .map(|(_, locale)| locale.clone())
+        .collect::<Vec<_>>();
+
+      locales.sort();
+      locales.dedup();
+
       let mut namespaces = HashMap::new();
 
-      for (locale, file) in files {
+      for ((namespace, locale), file) in files {
         namespaces
-          .entry(file.namespace)
+          .entry(namespace)
           .or_insert_with(HashMap::new)
-          .insert(locale, file.file);
+          .insert(locale, file);
       }
 
-      let (module, diagnostics) = build_namespaced_module(namespaces)?;
+      let (module, diagnostics) = build_namespaced_module(namespaces, options)?;
 
       Ok(ModuleBuildResult {
         module,
@@ -229,3 +268,38 @@ fn normalize_path(path: &Path, cwd: &Path, input_dir: &Path) -> String {
 
   path.display().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn namespaced_files_of_all_locales() {
+    let files = collect_namespaced(Path::new("tests/namespaced")).unwrap();
+    let mut keys = files
+      .keys()
+      .map(|(namespace, locale)| format!("{namespace}.{locale}"))
+      .collect::<Vec<_>>();
+    keys.sort();
+
+    assert_eq!(
+      keys,
+      [
+        "common.de",
+        "common.en",
+        "common.es",
+        "dashboard.de",
+        "dashboard.en",
+        "dashboard.es",
+        "notifications.de",
+        "notifications.en",
+        "notifications.es",
+      ]
+    );
+
+    let result = collect_and_build_modules("tests/namespaced").unwrap();
+    let namespaces = result.module.modules.keys().collect::<Vec<_>>();
+    assert_eq!(namespaces.len(), 3);
+    assert_eq!(result.locales.len(), 3);
+  }
+}
@@ -1,16 +1,18 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
 
-use miette::Diagnostic;
+use miette::{Diagnostic, LabeledSpan, SourceSpan};
 use owo_colors::OwoColorize;
 use thiserror::Error;
 
 use crate::{
-  interpolations::{Interpolation, InterpolationParseError, InterpolationType},
+  interpolations::{CustomType, InterpolationParseError, InterpolationType},
   parse::{Key, Locale, Message, Module},
 };
 
 pub struct Context<'a> {
   pub locale: &'a Locale,
+  pub custom_types: &'a [CustomType],
   pub normalized_file_path: &'a str,
   pub key_path: Vec<&'a str>,
   pub messages: &'a mut BTreeMap<Key, Message>,
@@ -19,43 +21,14 @@ pub struct Context<'a> {
 }
 
 impl Context<'_> {
-  pub fn add_interpolation_type_mismatches(
-    &mut self,
-    key: &str,
-    mismatches: Vec<(String, InterpolationType, Interpolation)>,
-  ) {
-    let key = self.path_at(key);
-    let locale = self.locale.clone();
-
-    for (name, found, existing) in mismatches.into_iter() {
-      let entry = self
-        .diagnostics
-        .interpolation_type_mismatches
-        .entry((key.clone(), name.clone()))
-        .or_default();
-
-      // Insert existing locales
-      for locale in existing.ranges.keys() {
-        entry.insert((locale.clone(), existing.type_));
-      }
-
-      // Insert found type
-      entry.insert((locale.clone(), found));
-    }
-  }
-
   pub fn add_key_diagnostics(&mut self, key: &str, diagnostic: KeyDiagnostic) {
     let key = self.path_at(key);
-    let locale = self.locale.clone();
-    let normalized_file_path = self.normalized_file_path.to_string();
-
-    let file_diagnostics = self
-      .diagnostics
-      .file_diagnostics
-      .entry((locale, normalized_file_path))
-      .or_default();
-
-    file_diagnostics.insert(key.clone(), diagnostic);
+    self.diagnostics.add_key_diagnostics(
+      self.locale.clone(),
+      self.normalized_file_path,
+      key,
+      diagnostic,
+    );
   }
 
   fn path_at(&self, key: &str) -> String {
@@ -69,19 +42,136 @@ impl Context<'_> {
   }
 }
 
+/// Problems found in translation files, which don't prevent generating messages
 #[derive(Debug, Default)]
 pub struct Diagnostics {
-  pub file_diagnostics: HashMap<(Locale, String), HashMap<String, KeyDiagnostic>>,
+  /// Diagnostics for each file, keyed by locale and normalized path, and then by key path
+  pub file_diagnostics: HashMap<(Locale, String), HashMap<String, Vec<KeyDiagnostic>>>,
+  /// Interpolations with different types between locales, keyed by key path and name
   pub interpolation_type_mismatches:
     HashMap<(String, String), HashSet<(Locale, InterpolationType)>>,
 }
 
+/// A problem with a single key of a translation file
 #[derive(Debug, Clone, Error, Diagnostic)]
 pub enum KeyDiagnostic {
   #[error("Unsupported value type: {}", value_type.purple())]
   #[diagnostic()]
   UnsupportedValueType { value_type: String },
 
+  #[error("Unsupported Fluent syntax: {}", construct.purple())]
+  #[diagnostic(help(
+    "Only text, variables, NUMBER() and select expressions on variables are supported"
+  ))]
+  UnsupportedFluentSyntax { construct: String },
+
+  #[error("Placeholders don't match the source text")]
+  #[diagnostic()]
+  PlaceholderMismatch {
+    #[source_code]
+    source_code: String,
+    #[label(collection, "Unknown placeholder")]
+    unknown: Vec<SourceSpan>,
+    /// Lists the missing placeholders, if any
+    #[help]
+    help: String,
+  },
+
+  #[error("Unsupported ICU message syntax: {reason}")]
+  #[diagnostic(help("Only placeholders, plural and select expressions are supported"))]
+  UnsupportedIcuSyntax {
+    #[source_code]
+    source_code: String,
+    #[label]
+    span: SourceSpan,
+    reason: String,
+  },
+
+  #[error("Key doesn't match any message")]
+  #[diagnostic(help("ARB keys are the camelCase key paths of existing messages"))]
+  UnknownArbKey,
+
+  #[error("Messages with references can't be imported from ARB files")]
+  #[diagnostic(help(
+    "ARB files contain the referenced text, edit translations with references like {{@common.brand}} in your translation files"
+  ))]
+  ArbReferences,
+
+  #[error("Invalid metadata {}, expected {expected}", name.purple())]
+  #[diagnostic(help(
+    "Messages with metadata are written as {{ text = \"...\", description = \"...\", maxLength = 20, deprecated = \"...\", tags = [\"...\"] }}. Inline tables with a string text and only these keys are messages, write modules with these keys as a [table]"
+  ))]
+  InvalidMetadata { name: String, expected: String },
+
+  #[error(
+    "Translation for {} is {length} characters long, but at most {max_length} are allowed",
+    locale.blue()
+  )]
+  #[diagnostic()]
+  TooLong {
+    #[source_code]
+    source_code: String,
+    #[label("exceeds the limit by {overflow}")]
+    span: SourceSpan,
+    locale: Locale,
+    length: usize,
+    max_length: usize,
+    overflow: usize,
+    /// Explains how the length is measured
+    #[help]
+    help: String,
+  },
+
+  #[error("Interpolations don't match {}", reference.blue())]
+  #[diagnostic()]
+  InterpolationMismatch {
+    #[source_code]
+    source_code: String,
+    /// Interpolations that aren't used in the reference locale, and where the missing ones would go
+    #[label(collection)]
+    labels: Vec<LabeledSpan>,
+    reference: Locale,
+    /// Lists the missing interpolations, if any
+    #[help]
+    help: Option<String>,
+  },
+
+  #[error("Select on {} uses keys that aren't cases of its type", name.cyan())]
+  #[diagnostic()]
+  UnknownCases {
+    #[source_code]
+    source_code: String,
+    #[label(collection, "Not a case")]
+    keys: Vec<SourceSpan>,
+    name: String,
+    /// Lists the cases of the type
+    #[help]
+    help: String,
+  },
+
+  #[error("Reference to unknown message {}", path.cyan())]
+  #[diagnostic()]
+  UnknownReference {
+    #[source_code]
+    source_code: String,
+    #[label("not found")]
+    span: SourceSpan,
+    path: String,
+    /// Explains whether the message doesn't exist or isn't translated
+    #[help]
+    help: String,
+  },
+
+  #[error("References form a cycle")]
+  #[diagnostic(help("References can't form a cycle, since their text would never end"))]
+  ReferenceCycle {
+    #[source_code]
+    source_code: String,
+    #[label("{cycle}")]
+    span: SourceSpan,
+    cycle: String,
+  },
+
   #[error("Interpolation errors found")]
   #[diagnostic()]
   InterpolationErrors {
@@ -93,45 +183,71 @@ pub enum KeyDiagnostic {
 }
 
 impl Diagnostics {
+  pub fn add_key_diagnostics(
+    &mut self,
+    locale: Locale,
+    normalized_file_path: &str,
+    key: String,
+    diagnostic: KeyDiagnostic,
+  ) {
+    let file_diagnostics = self
+      .file_diagnostics
+      .entry((locale, normalized_file_path.to_string()))
+      .or_default();
+
+    file_diagnostics.entry(key).or_default().push(diagnostic);
+  }
+
   pub fn is_empty(&self) -> bool {
     self.file_diagnostics.is_empty() && self.interpolation_type_mismatches.is_empty()
   }
 
-  pub fn report(&self) {
+  /// Renders all diagnostics as a human-readable report
+  pub fn render(&self) -> String {
+    let mut out = String::new();
     if self.is_empty() {
-      return;
+      return out;
     }
 
     let handler = miette::GraphicalReportHandler::new().with_show_related_as_nested(true);
-    let mut buf = String::new();
 
     for ((_locale, file), diagnostics) in self.file_diagnostics.iter() {
-      eprintln!("Errors in {}:", file.green());
-
-      for (key, diagnostic) in diagnostics {
-        buf.clear();
-        println!("Errors in key {}:", key.yellow());
-        let _ = handler.render_report(&mut buf, diagnostic);
-        eprintln!("{buf}");
+      let _ = writeln!(out, "Errors in {}:", file.green());
+
+      for (key, diagnostics) in diagnostics {
+        let _ = writeln!(out, "Errors in key {}:", key.yellow());
+        for diagnostic in diagnostics {
+          let _ = handler.render_report(&mut out, diagnostic);
+          let _ = writeln!(out);
+        }
       }
     }
 
     for ((key, name), mismatches) in self.interpolation_type_mismatches.iter() {
-      eprintln!(
+      let _ = writeln!(
+        out,
         "Interpolation {} in key {} has different types between locales:",
         name.cyan(),
         key.yellow()
       );
 
       for (locale, type_) in mismatches {
-        eprintln!(
+        let _ = writeln!(
+          out,
           "  • Locale {} defines type as: {}",
           locale.blue(),
           type_.purple()
         );
       }
 
-      eprintln!();
+      let _ = writeln!(out);
     }
+
+    out
+  }
+
+  /// Prints all diagnostics to stderr
+  pub fn report(&self) {
+    eprint!("{}", self.render());
   }
 }
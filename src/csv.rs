@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::collect::collect_and_build_modules_with;
+use crate::context::{Diagnostics, KeyDiagnostic};
+use crate::errors::WoofError;
+use crate::interpolations::{CustomType, parse_str_with_types};
+use crate::parse::{BuildOptions, Locale, Message, Module};
+use crate::update::Updater;
+
+static KEY_COLUMN: &str = "key";
+
+/// Writes a CSV file with one row per message and one column per locale. Rows are sorted by key
+/// path, in the same order as the generated code, and locales are sorted by name.
+pub fn export(path: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if path.is_dir() {
+    return Err(WoofError::OutputFileExists(
+      path.to_string_lossy().to_string(),
+    ));
+  }
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  let contents = write_csv(locales, module)
+    .map_err(|err| WoofError::InvalidCsvFile(path.display().to_string(), err.to_string()))?;
+
+  fs::write(path, contents)?;
+  Ok(())
+}
+
+/// Imports an edited CSV file into the TOML files of the input directory.
+///
+/// Only cells that differ from the current translations are written, and empty cells are ignored.
+/// Rows containing a translation with invalid interpolations are skipped entirely and reported
+/// as diagnostics. Returns the updated files and the number of changed translations in each.
+pub fn import(
+  input_dir: &Path,
+  file: &Path,
+  options: &BuildOptions,
+) -> Result<(Vec<(PathBuf, usize)>, Diagnostics), WoofError> {
+  let current = collect_and_build_modules_with(input_dir, options)?;
+  let messages = current
+    .module
+    .flatten_dotted()
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+
+  let filename = file.display().to_string();
+  let contents = fs::read_to_string(file)?;
+
+  let mut diagnostics = Diagnostics::default();
+  let changes = read_csv(
+    &filename,
+    &contents,
+    &messages,
+    &options.custom_types,
+    &mut diagnostics,
+  )?;
+
+  let mut updater = Updater::new(input_dir)?;
+  for (locale, key_path, translation) in changes {
+    updater.set_dotted(&locale, &key_path, &translation)?;
+  }
+
+  Ok((updater.save()?, diagnostics))
+}
+
+fn write_csv(locales: &[Locale], module: &Module) -> Result<String, csv::Error> {
+  let mut writer = csv::Writer::from_writer(Vec::new());
+
+  let mut locales = locales.iter().collect::<Vec<_>>();
+  locales.sort();
+
+  let mut header = vec![KEY_COLUMN];
+  header.extend(locales.iter().map(|locale| locale.0.as_str()));
+  writer.write_record(&header)?;
+
+  for (key_path, message) in module.flatten_dotted() {
+    let mut record = vec![key_path];
+    record.extend(locales.iter().map(|locale| {
+      message
+        .translation
+        .get(locale)
+        .map(|t| t.literal())
+        .unwrap_or_default()
+    }));
+
+    writer.write_record(&record)?;
+  }
+
+  let bytes = writer
+    .into_inner()
+    .map_err(|err| csv::Error::from(err.into_error()))?;
+
+  Ok(String::from_utf8(bytes).expect("csv writer only writes strings"))
+}
+
+/// Compares the rows of a CSV file with the current messages, returning the locale, key path and
+/// translation of every changed cell
+fn read_csv(
+  filename: &str,
+  contents: &str,
+  messages: &HashMap<String, &Message>,
+  custom_types: &[CustomType],
+  diagnostics: &mut Diagnostics,
+) -> Result<Vec<(Locale, String, String)>, WoofError> {
+  let invalid = |reason: String| WoofError::InvalidCsvFile(filename.to_string(), reason);
+
+  let mut reader = csv::Reader::from_reader(contents.as_bytes());
+  let header = reader
+    .headers()
+    .map_err(|err| invalid(err.to_string()))?
+    .clone();
+
+  let Some(key_column) = header.iter().position(|column| column == KEY_COLUMN) else {
+    return Err(invalid(format!("Missing {KEY_COLUMN} column")));
+  };
+
+  let mut changes = Vec::new();
+
+  for record in reader.records() {
+    let record = record.map_err(|err| invalid(err.to_string()))?;
+    let Some(key_path) = record.get(key_column).filter(|key| !key.is_empty()) else {
+      continue;
+    };
+
+    let message = messages.get(key_path);
+    let mut row = Vec::new();
+    let mut valid = true;
+
+    for (column, (locale, translation)) in header.iter().zip(record.iter()).enumerate() {
+      if column == key_column || locale.is_empty() || translation.is_empty() {
+        continue;
+      }
+
+      let locale = Locale(locale.to_string());
+      let current = message
+        .and_then(|m| m.translation.get(&locale))
+        .map(|t| t.literal());
+
+      if current.as_deref() == Some(translation) {
+        continue;
+      }
+
+      let parsed = parse_str_with_types(translation, custom_types);
+      if !parsed.errors.is_empty() {
+        valid = false;
+        diagnostics.add_key_diagnostics(
+          locale,
+          filename,
+          key_path.to_string(),
+          KeyDiagnostic::InterpolationErrors {
+            source_code: translation.to_string(),
+            errors: parsed.errors,
+          },
+        );
+
+        continue;
+      }
+
+      row.push((locale, key_path.to_string(), translation.to_string()));
+    }
+
+    if valid {
+      changes.extend(row);
+    }
+  }
+
+  Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+  use crate::interpolations::CustomType;
+
+  fn test_module() -> Module {
+    module(&[
+      (
+        "en",
+        r#"
+greeting = 'Hello, "{name}"!'
+multiline = "First\nSecond"
+
+[nav]
+home = "Home"
+"v1.0" = "Version 1.0"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo, `{name}`!"
+
+[nav]
+home = "Startseite"
+"#,
+      ),
+    ])
+  }
+
+  #[test]
+  fn export_csv() {
+    insta::assert_snapshot!(
+      write_csv(
+        &[Locale("de".to_string()), Locale("en".to_string())],
+        &test_module()
+      )
+      .unwrap()
+    );
+  }
+
+  #[test]
+  fn import_unchanged_csv() {
+    let module = test_module();
+    let messages = module.flatten_dotted().into_iter().collect();
+    let csv = write_csv(
+      &[Locale("de".to_string()), Locale("en".to_string())],
+      &module,
+    )
+    .unwrap();
+
+    let mut diagnostics = Diagnostics::default();
+    let changes = read_csv("messages.csv", &csv, &messages, &[], &mut diagnostics).unwrap();
+
+    assert!(changes.is_empty());
+    assert!(diagnostics.is_empty());
+  }
+
+  #[test]
+  fn import_changed_csv() {
+    let module = test_module();
+    let messages = module.flatten_dotted().into_iter().collect();
+    let csv = r#"en,key,de,fr
+"Hello, ""{name}""!",greeting,"Servus, {name}!",
+First,multiline,Erste,Premier {oops
+Home,nav.home,,Accueil
+Version 1.0,"nav.""v1.0""",Version 1.0,
+New,brand.new,Neu,
+"#;
+
+    let mut diagnostics = Diagnostics::default();
+    let changes = read_csv("messages.csv", csv, &messages, &[], &mut diagnostics);
+
+    insta::assert_debug_snapshot!((changes, diagnostics));
+  }
+
+  #[test]
+  fn import_custom_types_csv() {
+    let module = test_module();
+    let messages = module.flatten_dotted().into_iter().collect();
+    let money = CustomType {
+      name: "money".to_string(),
+      type_name: "Money".to_string(),
+      formatter: "formatMoney".to_string(),
+      module: "./money".to_string(),
+    };
+
+    let csv = "key,de\ngreeting,Summe: {total:money}\nnav.home,Summe: {total:currency}\n";
+
+    let mut diagnostics = Diagnostics::default();
+    let changes = read_csv("messages.csv", csv, &messages, &[money], &mut diagnostics).unwrap();
+
+    assert_eq!(
+      changes,
+      [(
+        Locale("de".to_string()),
+        "greeting".to_string(),
+        "Summe: {total:money}".to_string()
+      )]
+    );
+    assert_eq!(diagnostics.file_diagnostics.len(), 1);
+  }
+
+  #[test]
+  fn import_invalid_csv() {
+    let messages = HashMap::new();
+    let mut diagnostics = Diagnostics::default();
+
+    assert!(matches!(
+      read_csv(
+        "messages.csv",
+        "en,de\nHello,Hallo\n",
+        &messages,
+        &[],
+        &mut diagnostics
+      ),
+      Err(WoofError::InvalidCsvFile(..))
+    ));
+  }
+}
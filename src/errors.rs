@@ -1,5 +1,6 @@
 use thiserror::Error;
 
+/// Errors that prevent collecting, generating, exporting or importing messages
 #[derive(Debug, Error)]
 pub enum WoofError {
   #[error("Path is not a directory: {0}")]
@@ -15,7 +16,31 @@ pub enum WoofError {
   Io(#[from] std::io::Error),
 
   #[error("Error parsing translation file {0}: {1}")]
-  Toml(String, toml::de::Error),
+  Toml(String, toml_edit::TomlError),
+
+  #[error("Error parsing translation file {0}: {1}")]
+  Fluent(String, fluent_syntax::parser::ParserError),
+
+  #[error("Found multiple translation files for {0}")]
+  DuplicateFiles(String),
+
+  #[error("Invalid key path: {0}")]
+  InvalidKeyPath(String),
+
+  #[error("Can't write translations into {0}, only TOML files can be updated")]
+  ReadOnlyFile(String),
+
+  #[error("Invalid PO file {0} at line {1}: {2}")]
+  InvalidPoFile(String, usize, String),
+
+  #[error("Invalid CSV file {0}: {1}")]
+  InvalidCsvFile(String, String),
+
+  #[error("Invalid XLIFF file {0}: {1}")]
+  InvalidXliffFile(String, String),
+
+  #[error("Invalid ARB file {0}: {1}")]
+  InvalidArbFile(String, String),
 
   #[error("File exists at output path {0}")]
   OutputFileExists(String),
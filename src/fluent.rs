@@ -0,0 +1,390 @@
+use std::collections::HashSet;
+
+use fluent_syntax::{
+  ast::{Entry, Expression, InlineExpression, Pattern, PatternElement, VariantKey},
+  parser::{self, ParserError},
+  unicode::unescape_unicode_to_string,
+};
+use toml_edit::{Item, Table, value};
+
+use crate::context::KeyDiagnostic;
+
+/// Plural categories that Fluent matches number selectors against
+static PLURAL_CATEGORIES: &[&str] = &["zero", "one", "two", "few", "many", "other"];
+
+/// Key used for the value of a message that also has attributes
+static VALUE_KEY: &str = "value";
+
+#[derive(Debug)]
+pub struct FluentFile {
+  pub contents: Table,
+  /// Diagnostics for constructs that can't be represented, keyed by their key path
+  pub diagnostics: Vec<(String, KeyDiagnostic)>,
+}
+
+/// Converts a Fluent resource into the same structure that TOML files are parsed into.
+///
+/// Messages become translation strings, using woof's interpolation syntax for variables and
+/// select expressions. Messages with attributes become modules containing the attributes, with the
+/// message value (if any) stored under `value`. Message comments are kept as TOML comments.
+pub fn parse_fluent(source: &str) -> Result<FluentFile, ParserError> {
+  let resource = parser::parse(source).map_err(|(_, mut errors)| errors.remove(0))?;
+
+  let mut table = Table::new();
+  let mut diagnostics = Vec::new();
+
+  for entry in resource.body {
+    match entry {
+      Entry::Message(message) => {
+        let key = message.id.name;
+
+        if message.attributes.is_empty() {
+          let Some(pattern) = message.value else {
+            continue;
+          };
+
+          let s = Converter::new(key, &mut diagnostics).convert(&pattern);
+          table.insert(key, value(s));
+        } else {
+          let mut module = Table::new();
+
+          if let Some(pattern) = message.value {
+            let path = format!("{key}.{VALUE_KEY}");
+            let s = Converter::new(&path, &mut diagnostics).convert(&pattern);
+            module.insert(VALUE_KEY, value(s));
+          }
+
+          for attribute in message.attributes {
+            let path = format!("{key}.{}", attribute.id.name);
+            let s = Converter::new(&path, &mut diagnostics).convert(&attribute.value);
+            module.insert(attribute.id.name, value(s));
+          }
+
+          table.insert(key, Item::Table(module));
+        }
+
+        if let Some(comment) = message.comment
+          && let Some(mut key) = table.key_mut(key)
+        {
+          let prefix = comment
+            .content
+            .iter()
+            .map(|line| format!("# {line}\n"))
+            .collect::<String>();
+
+          key.leaf_decor_mut().set_prefix(prefix);
+        }
+      }
+
+      Entry::Term(term) => {
+        diagnostics.push((
+          format!("-{}", term.id.name),
+          KeyDiagnostic::UnsupportedFluentSyntax {
+            construct: "term definition".to_string(),
+          },
+        ));
+      }
+
+      Entry::Comment(_) | Entry::GroupComment(_) | Entry::ResourceComment(_) => {}
+
+      // Junk is only produced alongside parser errors, which we already returned
+      Entry::Junk { .. } => {}
+    }
+  }
+
+  Ok(FluentFile {
+    contents: table,
+    diagnostics,
+  })
+}
+
+struct Converter<'a> {
+  key: &'a str,
+  diagnostics: &'a mut Vec<(String, KeyDiagnostic)>,
+  /// Variables that are used as numbers anywhere in the pattern
+  numbers: HashSet<&'a str>,
+  /// Number of select variants around the current pattern element
+  variant_depth: usize,
+}
+
+impl<'a> Converter<'a> {
+  fn new(key: &'a str, diagnostics: &'a mut Vec<(String, KeyDiagnostic)>) -> Self {
+    Self {
+      key,
+      diagnostics,
+      numbers: HashSet::new(),
+      variant_depth: 0,
+    }
+  }
+
+  fn convert(mut self, pattern: &Pattern<&'a str>) -> String {
+    collect_numbers(pattern, &mut self.numbers);
+
+    let mut result = String::new();
+    self.write_pattern(&mut result, pattern);
+    result
+  }
+
+  fn write_pattern(&mut self, out: &mut String, pattern: &Pattern<&'a str>) {
+    for element in &pattern.elements {
+      match element {
+        PatternElement::TextElement { value } => out.push_str(&value.replace('{', "{{")),
+        PatternElement::Placeable { expression } => self.write_expression(out, expression),
+      }
+    }
+  }
+
+  fn write_expression(&mut self, out: &mut String, expression: &Expression<&'a str>) {
+    let (selector, variants) = match expression {
+      Expression::Inline(inline) => return self.write_inline(out, inline),
+      Expression::Select { selector, variants } => (selector, variants),
+    };
+
+    let Some(name) = variable_name(selector) else {
+      self.unsupported("select on anything other than a variable");
+
+      // Fall back to the default variant, so the message stays usable
+      if let Some(default) = variants.iter().find(|v| v.default) {
+        self.write_pattern(out, &default.value);
+      }
+
+      return;
+    };
+
+    out.push('{');
+    out.push_str(&self.variable(name));
+    out.push_str(" ->");
+
+    for variant in variants {
+      out.push(' ');
+      if variant.default {
+        out.push('*');
+      }
+
+      match &variant.key {
+        VariantKey::Identifier { name } => out.push_str(name),
+        VariantKey::NumberLiteral { value } => out.push_str(value),
+      }
+
+      out.push_str(" {");
+      self.variant_depth += 1;
+      self.write_pattern(out, &variant.value);
+      self.variant_depth -= 1;
+      out.push('}');
+    }
+
+    out.push('}');
+  }
+
+  fn write_inline(&mut self, out: &mut String, inline: &InlineExpression<&'a str>) {
+    match inline {
+      InlineExpression::StringLiteral { value } => {
+        let mut value = unescape_unicode_to_string(value).replace('{', "{{");
+
+        // Closing braces can't be escaped, in variants they would end the variant early
+        if self.variant_depth > 0 && value.contains('}') {
+          self.unsupported("closing brace in a select variant");
+          value = value.replace('}', "");
+        }
+
+        out.push_str(&value);
+      }
+      InlineExpression::NumberLiteral { value } => out.push_str(value),
+      InlineExpression::VariableReference { id } => {
+        out.push('{');
+        out.push_str(&self.variable(id.name));
+        out.push('}');
+      }
+      InlineExpression::FunctionReference { id, .. } => match variable_name(inline) {
+        Some(name) => {
+          out.push('{');
+          out.push_str(&self.variable(name));
+          out.push('}');
+        }
+        None => self.unsupported(&format!("function {}()", id.name)),
+      },
+      InlineExpression::MessageReference { id, .. } => {
+        self.unsupported(&format!("message reference {}", id.name));
+      }
+      InlineExpression::TermReference { id, .. } => {
+        self.unsupported(&format!("term reference -{}", id.name));
+      }
+      InlineExpression::Placeable { expression } => self.write_expression(out, expression),
+    }
+  }
+
+  fn variable(&self, name: &str) -> String {
+    if self.numbers.contains(name) {
+      format!("{name}:number")
+    } else {
+      name.to_string()
+    }
+  }
+
+  fn unsupported(&mut self, construct: &str) {
+    self.diagnostics.push((
+      self.key.to_string(),
+      KeyDiagnostic::UnsupportedFluentSyntax {
+        construct: construct.to_string(),
+      },
+    ));
+  }
+}
+
+/// Returns the variable name for `$name` and `NUMBER($name)` expressions
+fn variable_name<'a>(inline: &InlineExpression<&'a str>) -> Option<&'a str> {
+  match inline {
+    InlineExpression::VariableReference { id } => Some(id.name),
+    InlineExpression::FunctionReference { id, arguments }
+      if id.name == "NUMBER" && arguments.positional.len() == 1 && arguments.named.is_empty() =>
+    {
+      match &arguments.positional[0] {
+        InlineExpression::VariableReference { id } => Some(id.name),
+        _ => None,
+      }
+    }
+    _ => None,
+  }
+}
+
+/// Collects variables passed to `NUMBER()` or used to select between numeric or plural variants,
+/// since they have to be typed as numbers for the select to work
+fn collect_numbers<'a>(pattern: &Pattern<&'a str>, numbers: &mut HashSet<&'a str>) {
+  fn visit_inline<'a>(inline: &InlineExpression<&'a str>, numbers: &mut HashSet<&'a str>) {
+    match inline {
+      InlineExpression::FunctionReference { .. } => {
+        if let Some(name) = variable_name(inline) {
+          numbers.insert(name);
+        }
+      }
+      InlineExpression::Placeable { expression } => visit_expression(expression, numbers),
+      _ => {}
+    }
+  }
+
+  fn visit_expression<'a>(expression: &Expression<&'a str>, numbers: &mut HashSet<&'a str>) {
+    match expression {
+      Expression::Inline(inline) => visit_inline(inline, numbers),
+      Expression::Select { selector, variants } => {
+        visit_inline(selector, numbers);
+
+        let numeric = variants.iter().any(|v| match &v.key {
+          VariantKey::NumberLiteral { .. } => true,
+          VariantKey::Identifier { name } => PLURAL_CATEGORIES.contains(name) && *name != "other",
+        });
+
+        if numeric && let Some(name) = variable_name(selector) {
+          numbers.insert(name);
+        }
+
+        for variant in variants {
+          collect_numbers(&variant.value, numbers);
+        }
+      }
+    }
+  }
+
+  for element in &pattern.elements {
+    if let PatternElement::Placeable { expression } = element {
+      visit_expression(expression, numbers);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn convert(input: &str) -> (String, Vec<String>) {
+    let file = parse_fluent(input).unwrap();
+    let diagnostics = file
+      .diagnostics
+      .into_iter()
+      .map(|(key, diagnostic)| format!("{key}: {diagnostic:?}"))
+      .collect();
+
+    let document = toml_edit::DocumentMut::from(file.contents);
+    (document.to_string(), diagnostics)
+  }
+
+  #[test]
+  fn messages_and_variables() {
+    insta::assert_debug_snapshot!(convert(
+      r#"
+# Comments are kept
+hello = Hello, world!
+greeting = Hello, { $name }!
+count = You have { NUMBER($count) } items
+literal = Braces: { "{" } and {"}"}
+multiline =
+    First line
+    second line
+"#
+    ));
+  }
+
+  #[test]
+  fn attributes_as_modules() {
+    insta::assert_debug_snapshot!(convert(
+      r#"
+login = Log in
+    .title = Log into your account
+    .aria-label = Login button
+only-attributes =
+    .placeholder = Search { $scope }
+"#
+    ));
+  }
+
+  #[test]
+  fn select_expressions() {
+    insta::assert_debug_snapshot!(convert(
+      r#"
+emails = { $count ->
+    [0] No emails
+    [one] One email
+   *[other] { $count } emails
+}
+tier = { $tier ->
+    [free] Free plan
+   *[other] Paid plan for { $user }
+}
+"#
+    ));
+  }
+
+  #[test]
+  fn closing_braces_in_variants() {
+    let converted = convert(
+      r#"
+braces = { $count ->
+    [one] One {"{"}item{"}"}
+   *[other] Many { "{}" } items
+}
+outside = Outside {"}"}
+"#,
+    );
+
+    let document = converted.0.parse::<toml_edit::DocumentMut>().unwrap();
+    let braces = document["braces"].as_str().unwrap();
+    assert!(crate::interpolations::parse_str(braces).errors.is_empty());
+
+    insta::assert_debug_snapshot!(converted);
+  }
+
+  #[test]
+  fn unsupported_constructs() {
+    insta::assert_debug_snapshot!(convert(
+      r#"
+-brand = Woof
+welcome = Welcome to { -brand }
+date = Today is { DATETIME($date) }
+ref = See { hello }
+gender = { -brand.gender ->
+    [masculine] He
+   *[other] They
+}
+"#
+    ));
+  }
+}
@@ -1,14 +1,221 @@
+pub mod android;
+pub mod go;
+pub mod ios;
+pub mod javascript;
+pub mod kotlin;
+mod lookup;
+pub mod python;
+pub mod rust;
+pub mod swift;
+
+pub use lookup::{SizeEstimate, estimate_sizes};
+
 use crate::errors::WoofError;
-use crate::parse::{Locale, Module};
+use crate::interpolations::{CustomType, InterpolationType};
+use crate::parse::{
+  DEFAULT_LOCALE, Key, Locale, Message, Module, PLURAL_CATEGORIES, Part, Variant,
+};
+use crate::sanitize::unescape_translation;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::{BufWriter, Write};
 use std::iter::repeat_n;
 use std::path::Path;
 
-static DEFAULT_LOCALE: &str = "en";
 static VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+/// Plural rules for targets without built-in plural support. These cover common languages and
+/// integers, all other languages use the English rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PluralRule {
+  /// No plural forms, e.g. Japanese
+  Other,
+  /// `one` for exactly 1, e.g. English
+  One,
+  /// `one` for 0 up to 2, e.g. French
+  ZeroOne,
+  /// `one`, `few` and `many` by the last digits, e.g. Russian
+  EastSlavic,
+  /// Like [`PluralRule::EastSlavic`], but `one` only for exactly 1
+  Polish,
+  /// `one`, `few` for 2 to 4, and `many` for fractions, e.g. Czech
+  Czech,
+  /// All six plural categories
+  Arabic,
+}
+
+impl PluralRule {
+  fn for_locale(locale: &Locale) -> Self {
+    let language = locale
+      .0
+      .split(['-', '_'])
+      .next()
+      .unwrap_or_default()
+      .to_lowercase();
+
+    match language.as_str() {
+      "ja" | "zh" | "ko" | "vi" | "th" | "id" | "ms" | "lo" | "my" | "km" | "yue" => Self::Other,
+      "fr" | "pt" | "hi" | "bn" | "fa" | "gu" | "kn" | "zu" | "am" => Self::ZeroOne,
+      "ru" | "uk" | "be" => Self::EastSlavic,
+      "pl" => Self::Polish,
+      "cs" | "sk" => Self::Czech,
+      "ar" => Self::Arabic,
+      _ => Self::One,
+    }
+  }
+
+  /// Groups locales by their plural rule, in the order of their first locale. Locales with the
+  /// English rules are left out, since targets use them as the fallback.
+  fn group(locales: &[Locale]) -> Vec<(Self, Vec<&Locale>)> {
+    let mut rules = Vec::<(Self, Vec<&Locale>)>::new();
+    for locale in locales {
+      let rule = Self::for_locale(locale);
+      match rules.iter_mut().find(|(r, _)| *r == rule) {
+        Some((_, locales)) => locales.push(locale),
+        None => rules.push((rule, vec![locale])),
+      }
+    }
+
+    rules.retain(|(rule, _)| *rule != Self::One);
+    rules
+  }
+}
+
+/// The condition for choosing a variant of a select, for targets rendering selects as if-chains
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectCondition<'a> {
+  /// A boolean selector is `true`, or `false` when negated
+  Boolean(bool),
+  /// A string or enum selector equals the key
+  Equals(&'a str),
+  /// A number selector equals the key exactly
+  Number(f64),
+  /// The plural category of a number selector in the current locale is the key
+  PluralCategory(&'a str),
+}
+
+/// Returns the conditions of the non-default variants of a select, in the order they have to be
+/// checked. Number selectors match numeric variant keys exactly and everything else against the
+/// locale's plural category, with exact matches taking precedence. Boolean and enum selectors
+/// only match their cases, and variants that can never match are left out.
+fn select_conditions<'a>(
+  message: &Message,
+  name: &Key,
+  variants: &'a [Variant],
+) -> Vec<(SelectCondition<'a>, &'a Variant)> {
+  let type_ = message.interpolations.get(name).map(|i| &i.type_);
+  let cases = type_.and_then(InterpolationType::cases);
+
+  let mut conditions = variants
+    .iter()
+    .filter(|v| !v.default)
+    .filter(|v| {
+      cases
+        .as_ref()
+        .is_none_or(|cases| cases.contains(&v.key.as_str()))
+    })
+    .filter_map(|v| {
+      let key = v.key.as_str();
+      let condition = match type_ {
+        Some(InterpolationType::Boolean) => SelectCondition::Boolean(key == "true"),
+        Some(InterpolationType::Number) => match key.parse::<f64>() {
+          Ok(number) => SelectCondition::Number(number),
+          Err(_) if PLURAL_CATEGORIES.contains(&key) => SelectCondition::PluralCategory(key),
+          // Numbers can never match other keys
+          Err(_) => return None,
+        },
+        _ => SelectCondition::Equals(key),
+      };
+
+      Some((condition, v))
+    })
+    .collect::<Vec<_>>();
+
+  conditions.sort_by_key(|(condition, _)| matches!(condition, SelectCondition::PluralCategory(_)));
+  conditions
+}
+
+/// How the generated TypeScript modules import each other
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ImportStyle {
+  /// Directory imports like `./about`, as resolved by bundlers and `moduleResolution: bundler`
+  #[default]
+  Bundler,
+  /// Fully specified paths like `./about/index.js`, as required by `moduleResolution: NodeNext`
+  NodeNext,
+  /// Fully specified paths with TypeScript extensions like `./about/index.ts`, as required by Deno
+  Deno,
+}
+
+/// Options for the TypeScript output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypescriptOptions {
+  pub import_style: ImportStyle,
+  /// Write `.mts` files instead of `.ts` files
+  pub mts: bool,
+  /// Write all messages into a single file instead of a directory tree
+  pub single_file: bool,
+  /// Write the translations into a dictionary per locale and module, which are loaded with
+  /// `loadLocale`. Ignored for single files.
+  pub lazy: bool,
+  /// Look up translations with a `switch` or a record instead of an `if` per locale, whichever
+  /// is smaller after minification. Not used with [`TypescriptOptions::lazy`].
+  pub lookup_tables: bool,
+}
+
+impl TypescriptOptions {
+  fn extension(&self) -> &'static str {
+    if self.mts { "mts" } else { "ts" }
+  }
+
+  /// Specifier for importing the file at the given path, without its extension
+  fn file_import(&self, path: &str) -> String {
+    match (self.import_style, self.mts) {
+      (ImportStyle::Bundler, false) => path.to_string(),
+      (ImportStyle::Deno, _) => format!("{path}.{}", self.extension()),
+      // Bundlers only resolve `.mts` files from `.mjs` specifiers
+      (_, true) => format!("{path}.mjs"),
+      (ImportStyle::NodeNext, false) => format!("{path}.js"),
+    }
+  }
+
+  /// Specifier for importing the index file of the given directory
+  fn dir_import(&self, dir: &str) -> String {
+    if self.import_style == ImportStyle::Bundler && !self.mts {
+      dir.to_string()
+    } else {
+      self.file_import(&format!("{dir}/index"))
+    }
+  }
+}
+
+/// Writes TypeScript modules mirroring the messages into the given directory, replacing its
+/// previous contents. Every message becomes a function returning the translation for the
+/// current locale.
+///
+/// With [`TypescriptOptions::single_file`], the messages are written into a single file at the
+/// given path instead, adding the extension if it has none.
+pub fn generate(
+  dir: &Path,
+  locales: &[Locale],
+  module: &Module,
+  options: &TypescriptOptions,
+) -> Result<(), WoofError> {
+  if options.single_file {
+    let path = if dir.extension().is_some() {
+      dir.to_path_buf()
+    } else {
+      dir.with_extension(options.extension())
+    };
+
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, write_single_file(locales, module, options))?;
+    return Ok(());
+  }
+
   if dir.is_file() {
     return Err(WoofError::OutputFileExists(
       dir.to_string_lossy().to_string(),
@@ -20,38 +227,48 @@ pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), W
   }
 
   fs::create_dir_all(dir)?;
-  let mut locales_union = locales
-    .iter()
-    .map(|s| format!("\"{s}\""))
-    .collect::<Vec<_>>();
+  let locales_union = locales_union(locales);
 
-  locales_union.sort();
-  let locales_union = locales_union.join(" | ");
-
-  fs::write(
-    dir.join("index.ts"),
-    format!(
-      r#"// Code generated by woof v{VERSION}. DO NOT EDIT.
+  let mut index = format!(
+    r#"// Code generated by woof v{VERSION}. DO NOT EDIT.
 // eslint-disable
 // biome-ignore-all: generated code
 export type Locale = {locales_union}
 export const defaultLocale = "{DEFAULT_LOCALE}"
 export let getLocale: () => Locale = () => "{DEFAULT_LOCALE}"
 export const setLocaleFn = (fn: () => Locale) => (getLocale = fn)
-export * as m from "./root""#
-    ),
-  )?;
+export * as m from "{root}""#,
+    root = options.file_import("./root")
+  );
+
+  if options.lazy {
+    index.push_str(&write_loaders(locales, module, options));
+  }
 
-  write_module(dir, 0, module, &locales_union)
+  fs::write(dir.join(format!("index.{}", options.extension())), index)?;
+  write_module(dir, "", module, locales, options)
 }
 
-fn write_module(dir: &Path, depth: usize, module: &Module, locales: &str) -> Result<(), WoofError> {
+/// Writes the module at the given key path, joined with `/` like its directory
+fn write_module(
+  dir: &Path,
+  path: &str,
+  module: &Module,
+  locales: &[Locale],
+  options: &TypescriptOptions,
+) -> Result<(), WoofError> {
   if module.messages.is_empty() && module.modules.is_empty() {
     return Ok(());
   }
 
-  let filename = if depth == 0 { "root.ts" } else { "index.ts" };
-  let f = fs::File::create(dir.join(filename))?;
+  let depth = if path.is_empty() {
+    0
+  } else {
+    path.split('/').count()
+  };
+
+  let filename = if depth == 0 { "root" } else { "index" };
+  let f = fs::File::create(dir.join(format!("{filename}.{}", options.extension())))?;
   let mut f = BufWriter::new(f);
 
   let root_import = if depth == 0 {
@@ -65,57 +282,1085 @@ fn write_module(dir: &Path, depth: usize, module: &Module, locales: &str) -> Res
   writeln!(&mut f, "// biome-ignore-all: generated code")?;
 
   if !module.messages.is_empty() {
-    writeln!(&mut f, "import {{ getLocale }} from \"{root_import}\"")?;
-  }
+    let imports = if options.lazy {
+      "dictionaries, getLocale"
+    } else {
+      "getLocale"
+    };
 
-  for (key, message) in module.messages.iter() {
-    write!(&mut f, "export const {key} = (", key = key.sanitized)?;
-
-    if !message.interpolations.is_empty() {
-      write!(&mut f, "args: {{ ")?;
-
-      for (key, interpolation) in message.interpolations.iter() {
-        write!(
-          &mut f,
-          "{name}: {type_}; ",
-          name = key.sanitized,
-          type_ = interpolation.type_.as_typescript_type()
-        )?;
-      }
+    writeln!(
+      &mut f,
+      "import {{ {imports} }} from \"{}\"",
+      options.dir_import(&root_import)
+    )?;
 
-      write!(&mut f, "}}, ")?;
-    }
+    // Lazily loaded templates call the formatters in the dictionaries
+    let custom_imports = if options.lazy {
+      CustomImports::Types
+    } else {
+      CustomImports::Both
+    };
 
-    writeln!(&mut f, "locale?: {locales}) => {{")?;
-    writeln!(&mut f, "  const resolved = locale ?? getLocale()")?;
+    write!(
+      &mut f,
+      "{}",
+      custom_type_imports(module.messages.values(), depth, custom_imports, |path| {
+        options.file_import(path)
+      })
+    )?;
+  }
 
-    for locale in message.translation.keys() {
-      if let Some(template) = message.template_for_locale(locale) {
-        writeln!(
-          &mut f,
-          "  if (resolved === \"{locale}\") return `{}`",
-          template,
-        )?;
-      }
-    }
+  let locales_union = locales_union(locales);
+  for (key, message) in module.messages.iter() {
+    write!(&mut f, "{}", message_jsdoc(message, ""))?;
+    writeln!(
+      &mut f,
+      "export const {key} = ({params}) => {{",
+      key = key.sanitized,
+      params = typed_params(message, &locales_union)
+    )?;
 
-    writeln!(&mut f, "  return `{}`", key.sanitized)?;
-    writeln!(&mut f, "}}")?;
+    if options.lazy {
+      write!(&mut f, "{}", lazy_function_body(path, key, message))?;
+    } else {
+      write!(&mut f, "{}", message_body(key, message, options))?;
+    }
   }
 
-  for module_name in module.modules.keys() {
+  for (module_name, submodule) in module.modules.iter() {
+    write!(&mut f, "{}", module_jsdoc(submodule, ""))?;
     writeln!(
       &mut f,
-      "export * as {name} from \"./{name}\"",
-      name = module_name.sanitized
+      "export * as {name} from \"{path}\"",
+      name = module_name.sanitized,
+      path = options.dir_import(&format!("./{}", module_name.sanitized))
     )?;
   }
 
+  if options.lazy && !module.messages.is_empty() {
+    for locale in locales {
+      fs::write(
+        dir.join(format!("messages.{locale}.{}", options.extension())),
+        write_dictionary(locale, depth, module, options),
+      )?;
+    }
+  }
+
   for (module_name, module) in module.modules.iter() {
     let dir = dir.join(&module_name.sanitized);
+    let path = if path.is_empty() {
+      module_name.sanitized.clone()
+    } else {
+      format!("{path}/{}", module_name.sanitized)
+    };
+
     fs::create_dir_all(&dir)?;
-    write_module(&dir, depth + 1, module, locales)?;
+    write_module(&dir, &path, module, locales, options)?;
   }
 
   Ok(())
 }
+
+/// Key paths of all modules with messages, which get a dictionary per locale when loading lazily
+fn dictionary_paths(path: &str, module: &Module) -> Vec<String> {
+  let mut paths = Vec::new();
+  if !module.messages.is_empty() {
+    paths.push(path.to_string());
+  }
+
+  for (key, module) in &module.modules {
+    let path = if path.is_empty() {
+      key.sanitized.clone()
+    } else {
+      format!("{path}/{}", key.sanitized)
+    };
+
+    paths.extend(dictionary_paths(&path, module));
+  }
+
+  paths
+}
+
+/// Path of a module's dictionary for a locale, relative to the output directory and without its
+/// extension. Key paths can't contain dots, so dictionaries don't collide with module directories.
+fn dictionary_import(path: &str, locale: &Locale, options: &TypescriptOptions) -> String {
+  if path.is_empty() {
+    options.file_import(&format!("./messages.{locale}"))
+  } else {
+    options.file_import(&format!("./{path}/messages.{locale}"))
+  }
+}
+
+/// Declares the loaded dictionaries and `loadLocale`, which imports all dictionaries of a locale.
+/// The type of each dictionary combines the exports of all locales, so messages that are missing
+/// in some locales can still be looked up.
+fn write_loaders(locales: &[Locale], module: &Module, options: &TypescriptOptions) -> String {
+  let paths = dictionary_paths("", module);
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  let mut out = String::from("\n\ntype Dictionaries = {\n");
+  for path in &paths {
+    let types = locales
+      .iter()
+      .map(|locale| {
+        format!(
+          "typeof import(\"{}\")",
+          dictionary_import(path, locale, options)
+        )
+      })
+      .collect::<Vec<_>>()
+      .join(" & ");
+
+    out.push_str(&format!("  \"{path}\": Partial<{types}>\n"));
+  }
+
+  out.push_str("}\n\nexport const dictionaries: { [L in Locale]?: Dictionaries } = {}\n\n");
+  out.push_str("const loaders: Record<Locale, () => Promise<Dictionaries>> = {\n");
+
+  for locale in &locales {
+    out.push_str(&format!("  \"{locale}\": () =>\n    Promise.all([\n"));
+    for path in &paths {
+      out.push_str(&format!(
+        "      import(\"{}\"),\n",
+        dictionary_import(path, locale, options)
+      ));
+    }
+
+    out.push_str("    ]).then((loaded) => ({\n");
+    for (i, path) in paths.iter().enumerate() {
+      out.push_str(&format!("      \"{path}\": loaded[{i}],\n"));
+    }
+
+    out.push_str("    })),\n");
+  }
+
+  out.push_str(
+    r#"}
+
+export const loadLocale = async (locale: Locale) => {
+  dictionaries[locale] ??= await loaders[locale]()
+}
+"#,
+  );
+
+  out
+}
+
+/// Writes the translations of a module for a single locale, as functions taking the same
+/// arguments as the message functions
+fn write_dictionary(
+  locale: &Locale,
+  depth: usize,
+  module: &Module,
+  options: &TypescriptOptions,
+) -> String {
+  let mut out = format!(
+    "// Code generated by woof v{VERSION}. DO NOT EDIT.\n// eslint-disable\n// biome-ignore-all: generated code\n"
+  );
+
+  let translated = module
+    .messages
+    .values()
+    .filter(|message| message.translation.contains_key(locale));
+  out.push_str(&custom_type_imports(
+    translated,
+    depth,
+    CustomImports::Both,
+    |path| options.file_import(path),
+  ));
+
+  let mut empty = true;
+  for (key, message) in &module.messages {
+    if let Some(template) = message.template_for_locale(locale) {
+      out.push_str(&format!(
+        "export const {key} = ({args}) => `{template}`\n",
+        key = key.sanitized,
+        args = typed_args(message)
+      ));
+      empty = false;
+    }
+  }
+
+  // Files without exports aren't modules, and can't be imported
+  if empty {
+    out.push_str("export {}\n");
+  }
+
+  out
+}
+
+/// Body of a message function looking up the translation in the loaded dictionaries. Returns the
+/// key if the locale isn't loaded yet or has no translation.
+fn lazy_function_body(path: &str, key: &Key, message: &Message) -> String {
+  let args = if message.interpolations.is_empty() {
+    ""
+  } else {
+    "args"
+  };
+
+  format!(
+    "  const resolved = locale ?? getLocale()\n  return dictionaries[resolved]?.[\"{path}\"]?.{key}?.({args}) ?? `{key}`\n}}\n",
+    key = key.sanitized
+  )
+}
+
+/// Writes all messages as top-level functions named after their key path, so they can be
+/// imported and tree-shaken individually. Every top-level module is exported as an object nesting
+/// its messages, and `m` nests those objects and the root messages like the modules of the
+/// directory tree. Objects keep all of their properties, so importing `m` includes every message.
+fn write_single_file(locales: &[Locale], module: &Module, options: &TypescriptOptions) -> String {
+  let locales = locales_union(locales);
+  let out = format!(
+    r#"// Code generated by woof v{VERSION}. DO NOT EDIT.
+// eslint-disable
+// biome-ignore-all: generated code
+{imports}export type Locale = {locales}
+export const defaultLocale = "{DEFAULT_LOCALE}"
+export let getLocale: () => Locale = () => "{DEFAULT_LOCALE}"
+export const setLocaleFn = (fn: () => Locale) => (getLocale = fn)
+"#,
+    imports = custom_type_imports(
+      module.flatten().into_iter().map(|(_, message)| message),
+      0,
+      CustomImports::Both,
+      |path| options.file_import(path)
+    )
+  );
+
+  let mut file = SingleFile {
+    out,
+    tree: String::new(),
+    names: ["Locale", "defaultLocale", "getLocale", "setLocaleFn", "m"]
+      .map(String::from)
+      .into_iter()
+      .collect(),
+    locales: &locales,
+    options,
+  };
+
+  file.write_module("", 1, module);
+  format!("{}export const m = {{\n{}}}\n", file.out, file.tree)
+}
+
+/// The functions of a single file written so far, and the properties of `m` nesting them
+struct SingleFile<'a> {
+  out: String,
+  tree: String,
+  /// Names of the exports, which flattened key paths must not collide with
+  names: HashSet<String>,
+  locales: &'a str,
+  options: &'a TypescriptOptions,
+}
+
+impl SingleFile<'_> {
+  fn write_module(&mut self, prefix: &str, depth: usize, module: &Module) {
+    let indent = "  ".repeat(depth);
+
+    for (key, message) in &module.messages {
+      let name = self.unique_name(format!("{prefix}{}", key.sanitized));
+
+      self.out.push_str(&message_jsdoc(message, ""));
+      self.out.push_str(&format!(
+        "export const {name} = ({params}) => {{\n",
+        params = typed_params(message, self.locales)
+      ));
+      self.out.push_str(&message_body(key, message, self.options));
+
+      // Properties don't show the documentation of the functions they refer to
+      self.tree.push_str(&message_jsdoc(message, &indent));
+      if name == key.sanitized {
+        self.tree.push_str(&format!("{indent}{name},\n"));
+      } else {
+        self
+          .tree
+          .push_str(&format!("{indent}{}: {name},\n", key.sanitized));
+      }
+
+      self.names.insert(name);
+    }
+
+    for (key, module) in &module.modules {
+      let is_top_level = prefix.is_empty();
+      let prefix = format!("{prefix}{}_", key.sanitized);
+      self.tree.push_str(&module_jsdoc(module, &indent));
+
+      if !is_top_level {
+        self
+          .tree
+          .push_str(&format!("{indent}{}: {{\n", key.sanitized));
+        self.write_module(&prefix, depth + 1, module);
+        self.tree.push_str(&format!("{indent}}},\n"));
+        continue;
+      }
+
+      // Top-level modules are exported on their own, so importing one keeps only its messages
+      let parent = std::mem::take(&mut self.tree);
+      self.write_module(&prefix, 1, module);
+      let tree = std::mem::replace(&mut self.tree, parent);
+
+      let name = self.unique_name(key.sanitized.clone());
+      self.out.push_str(&module_jsdoc(module, ""));
+      self
+        .out
+        .push_str(&format!("export const {name} = {{\n{tree}}}\n"));
+
+      if name == key.sanitized {
+        self.tree.push_str(&format!("{indent}{name},\n"));
+      } else {
+        self
+          .tree
+          .push_str(&format!("{indent}{}: {name},\n", key.sanitized));
+      }
+
+      self.names.insert(name);
+    }
+  }
+
+  /// Appends underscores to a name until it doesn't collide with another export. Flattened key
+  /// paths can collide, e.g. `a.b_c` and `a_b.c`.
+  fn unique_name(&self, mut name: String) -> String {
+    while self.names.contains(&name) {
+      name.push('_');
+    }
+
+    name
+  }
+}
+
+/// The `Locale` type as a sorted union of string literals
+fn locales_union(locales: &[Locale]) -> String {
+  let mut union = locales
+    .iter()
+    .map(|s| format!("\"{s}\""))
+    .collect::<Vec<_>>();
+
+  union.sort();
+  union.join(" | ")
+}
+
+/// The comment of a message or module, preferring the default locale's
+fn comment(comments: &BTreeMap<Locale, String>) -> Option<&String> {
+  comments
+    .get(&Locale(DEFAULT_LOCALE.to_string()))
+    .or_else(|| comments.values().next())
+}
+
+/// JSDoc with the description or comment of a message and its text in the default locale, so
+/// editors show the actual copy on hover. Deprecated messages are marked as such.
+fn message_jsdoc(message: &Message, indent: &str) -> String {
+  let mut lines = message
+    .metadata
+    .description
+    .as_ref()
+    .or_else(|| comment(&message.comments))
+    .map(|comment| comment.lines().map(String::from).collect::<Vec<_>>())
+    .unwrap_or_default();
+
+  let text = message
+    .translation
+    .get(&Locale(DEFAULT_LOCALE.to_string()))
+    .or_else(|| message.translation.values().next());
+
+  if let Some(text) = text {
+    if !lines.is_empty() {
+      lines.push(String::new());
+    }
+
+    lines.extend(text.literal().lines().map(|line| format!("> {line}")));
+  }
+
+  if let Some(reason) = &message.metadata.deprecated {
+    if !lines.is_empty() {
+      lines.push(String::new());
+    }
+
+    lines.push(format!("@deprecated {reason}").trim_end().to_string());
+  }
+
+  jsdoc(&lines, indent)
+}
+
+/// JSDoc with the comment of a module's table, if it has one
+fn module_jsdoc(module: &Module, indent: &str) -> String {
+  let lines = comment(&module.comments)
+    .map(|comment| comment.lines().map(String::from).collect::<Vec<_>>())
+    .unwrap_or_default();
+
+  jsdoc(&lines, indent)
+}
+
+fn jsdoc(lines: &[String], indent: &str) -> String {
+  if lines.is_empty() {
+    return String::new();
+  }
+
+  let mut out = format!("{indent}/**\n");
+  for line in lines {
+    if line.is_empty() {
+      out.push_str(&format!("{indent} *\n"));
+    } else {
+      // The comment would end early otherwise
+      out.push_str(&format!("{indent} * {}\n", line.replace("*/", "*\\/")));
+    }
+  }
+
+  out.push_str(&format!("{indent} */\n"));
+  out
+}
+
+/// The `args` parameter of a message function with its interpolations typed as TypeScript, or
+/// nothing if it has no interpolations
+fn typed_args(message: &Message) -> String {
+  if message.interpolations.is_empty() {
+    return String::new();
+  }
+
+  let mut args = String::from("args: { ");
+  for (key, interpolation) in message.interpolations.iter() {
+    args.push_str(&format!(
+      "{name}{optional}: {type_}; ",
+      name = key.sanitized,
+      optional = if interpolation.required { "" } else { "?" },
+      type_ = interpolation.type_.as_typescript_type()
+    ));
+  }
+
+  args.push('}');
+  args
+}
+
+/// What to import of the custom types used by a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CustomImports {
+  /// The formatters called by the templates
+  Formatters,
+  /// The types of the arguments
+  Types,
+  Both,
+}
+
+/// Imports of the custom types used by the given messages, grouped by module. Relative modules
+/// are resolved from the output directory, so they're prefixed with `../` for each level the
+/// importing file is nested by. Relative modules without an extension are passed to
+/// `file_import`, which adds the extension required by the import style.
+fn custom_type_imports<'a>(
+  messages: impl IntoIterator<Item = &'a Message>,
+  depth: usize,
+  imports: CustomImports,
+  file_import: impl Fn(&str) -> String,
+) -> String {
+  let mut modules: BTreeMap<&str, Vec<&CustomType>> = BTreeMap::new();
+  for message in messages {
+    for interpolation in message.interpolations.values() {
+      if let InterpolationType::Custom(custom) = &interpolation.type_ {
+        let types = modules.entry(&custom.module).or_default();
+        if !types.contains(&custom) {
+          types.push(custom);
+        }
+      }
+    }
+  }
+
+  let mut out = String::new();
+  for (module, types) in modules {
+    let mut names = Vec::new();
+    for custom in types {
+      let formatter = (imports != CustomImports::Types).then(|| custom.formatter.clone());
+      let type_name = match imports {
+        CustomImports::Formatters => None,
+        CustomImports::Types => Some(custom.type_name.clone()),
+        CustomImports::Both => Some(format!("type {}", custom.type_name)),
+      };
+
+      // Custom types can share a formatter or a type
+      for name in [formatter, type_name].into_iter().flatten() {
+        if !names.contains(&name) {
+          names.push(name);
+        }
+      }
+    }
+
+    let module = if !module.starts_with('.') {
+      module.to_string()
+    } else {
+      let module = if depth > 0 {
+        let parent = repeat_n("..", depth).collect::<Vec<_>>().join("/");
+        format!("{parent}/{}", module.strip_prefix("./").unwrap_or(module))
+      } else {
+        module.to_string()
+      };
+
+      if Path::new(&module).extension().is_none() {
+        file_import(&module)
+      } else {
+        module
+      }
+    };
+
+    let keyword = if imports == CustomImports::Types {
+      "import type"
+    } else {
+      "import"
+    };
+
+    out.push_str(&format!(
+      "{keyword} {{ {} }} from \"{module}\"\n",
+      names.join(", ")
+    ));
+  }
+
+  out
+}
+
+/// Parameters of a message function, with the interpolations typed as TypeScript
+fn typed_params(message: &Message, locales: &str) -> String {
+  let args = typed_args(message);
+  if args.is_empty() {
+    format!("locale?: {locales}")
+  } else {
+    format!("{args}, locale?: {locales}")
+  }
+}
+
+/// Body of a message function, which is the same in TypeScript and JavaScript. Returns the key
+/// if there's no translation for the locale.
+fn function_body(key: &Key, message: &Message) -> String {
+  let mut body = String::from("  const resolved = locale ?? getLocale()\n");
+
+  for locale in message.translation.keys() {
+    if let Some(template) = message.template_for_locale(locale) {
+      body.push_str(&format!(
+        "  if (resolved === \"{locale}\") return `{template}`\n"
+      ));
+    }
+  }
+
+  body.push_str(&format!("  return `{}`\n}}\n", key.sanitized));
+  body
+}
+
+/// Body of a message function, using lookup tables if enabled
+fn message_body(key: &Key, message: &Message, options: &TypescriptOptions) -> String {
+  if options.lookup_tables {
+    lookup::lookup_function_body(key, message)
+  } else {
+    function_body(key, message)
+  }
+}
+
+/// A language that declares the module tree as nested namespaces in a single file, like Kotlin
+/// objects or Swift enums. Output is indented by four spaces per level, and declarations are
+/// separated by blank lines.
+trait Namespaces {
+  fn write_message(&self, out: &mut String, key: &Key, message: &Message, indent: &str);
+  fn open_namespace(&self, out: &mut String, key: &Key, indent: &str);
+  fn close_namespace(&self, out: &mut String, indent: &str);
+}
+
+/// Walks a module like [`write_module`], writing its messages first and then its submodules as
+/// nested namespaces
+fn write_namespaces(out: &mut String, depth: usize, module: &Module, language: &impl Namespaces) {
+  let indent = "    ".repeat(depth);
+
+  // Declarations are separated by blank lines, except at the start of a namespace
+  let separate = |out: &mut String| {
+    if !out.ends_with("{\n") {
+      out.push('\n');
+    }
+  };
+
+  for (key, message) in &module.messages {
+    separate(out);
+    language.write_message(out, key, message, &indent);
+  }
+
+  for (key, module) in &module.modules {
+    separate(out);
+    language.open_namespace(out, key, &indent);
+    write_namespaces(out, depth + 1, module, language);
+    language.close_namespace(out, &indent);
+  }
+}
+
+/// The locale used before a locale getter is set, the default locale if there are translations
+/// for it
+fn initial_locale(locales: &[Locale]) -> Option<&Locale> {
+  locales
+    .iter()
+    .find(|locale| locale.0 == DEFAULT_LOCALE)
+    .or(locales.first())
+}
+
+/// Renders parts as a printf-style format string, as used by the native platforms. Interpolations
+/// become positional placeholders, numbered by the order of the message's arguments.
+///
+/// Native string files can't express select expressions, so their default variant is used.
+fn format_string(
+  message: &Message,
+  parts: &[Part],
+  placeholder: fn(usize, &InterpolationType) -> String,
+) -> String {
+  let mut out = String::new();
+
+  for part in parts {
+    match part {
+      Part::Text(text) => {
+        let text = unescape_translation(text);
+
+        // Strings without arguments aren't formatted, so percent signs stay as they are
+        if message.interpolations.is_empty() {
+          out.push_str(&text);
+        } else {
+          out.push_str(&text.replace('%', "%%"));
+        }
+      }
+      Part::Interpolation { name: key, .. } => {
+        if let Some(position) = message.interpolations.keys().position(|k| k == key) {
+          out.push_str(&placeholder(
+            position + 1,
+            &message.interpolations[key].type_,
+          ));
+        }
+      }
+      Part::Select { variants, .. } => {
+        if let Some(default) = variants.iter().find(|v| v.default) {
+          out.push_str(&format_string(message, &default.parts, placeholder));
+        }
+      }
+    }
+  }
+
+  out
+}
+
+/// Converts a locale like `pt-BR` into a PascalCase identifier like `PtBr`
+fn locale_ident(locale: &Locale) -> String {
+  let mut name = locale
+    .0
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .map(|subtag| {
+      let mut chars = subtag.chars();
+      chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase())
+        .unwrap_or_default()
+    })
+    .collect::<String>();
+
+  if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+    name.insert(0, 'L');
+  }
+
+  name
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use crate::collect::ParsedFile;
+  use crate::parse::{BuildOptions, build_flat_module};
+  use std::collections::HashMap;
+
+  /// Builds a module from the contents of flat translation files
+  pub(crate) fn module(files: &[(&str, &str)]) -> Module {
+    module_with(files, &BuildOptions::default())
+  }
+
+  pub(crate) fn module_with(files: &[(&str, &str)], options: &BuildOptions) -> Module {
+    let files = files
+      .iter()
+      .map(|(locale, contents)| {
+        let file = ParsedFile {
+          normalized_path: format!("{locale}.toml"),
+          contents: contents
+            .parse::<toml_edit::DocumentMut>()
+            .unwrap()
+            .as_table()
+            .clone(),
+          diagnostics: Vec::new(),
+        };
+
+        (Locale(locale.to_string()), file)
+      })
+      .collect::<HashMap<_, _>>();
+
+    build_flat_module(files, options).unwrap().0
+  }
+
+  #[test]
+  fn plural_rule_groups() {
+    let locales = ["en", "ru", "fr", "de", "uk", "pt-BR"].map(|l| Locale(l.to_string()));
+    let groups = PluralRule::group(&locales)
+      .into_iter()
+      .map(|(rule, locales)| (rule, locales.iter().map(|l| l.0.as_str()).collect()))
+      .collect::<Vec<(_, Vec<_>)>>();
+
+    assert_eq!(
+      groups,
+      [
+        (PluralRule::EastSlavic, vec!["ru", "uk"]),
+        (PluralRule::ZeroOne, vec!["fr", "pt-BR"]),
+      ]
+    );
+  }
+
+  #[test]
+  fn select_condition_kinds() {
+    let module = module(&[(
+      "en",
+      r#"
+items = "{count:number -> other {Other} one {One} 0 {None} few {Few} *many {Many}}"
+enabled = "{on:boolean -> false {Off} *true {On}}"
+size = "{size:enum(s|m) -> s {Small} x {Unknown} *m {Medium}}"
+reply = "{gender -> female {She} *other {They}}"
+"#,
+    )]);
+
+    let conditions = |key: &str, name: &str| {
+      let message = &module.messages[&Key::new(key)];
+      let parts = message.parts_for_locale(&Locale("en".to_string())).unwrap();
+      let Some(Part::Select { variants, .. }) = parts.into_iter().next() else {
+        panic!("{key} isn't a select");
+      };
+
+      select_conditions(message, &Key::new(name), &variants)
+        .into_iter()
+        .map(|(condition, variant)| format!("{condition:?} => {}", variant.key))
+        .collect::<Vec<_>>()
+    };
+
+    insta::assert_debug_snapshot!([
+      conditions("items", "count"),
+      conditions("enabled", "on"),
+      conditions("size", "size"),
+      conditions("reply", "gender"),
+    ]);
+  }
+
+  #[test]
+  fn import_specifiers() {
+    let styles = [
+      ImportStyle::Bundler,
+      ImportStyle::NodeNext,
+      ImportStyle::Deno,
+    ];
+    let specifiers = styles
+      .iter()
+      .flat_map(|&import_style| {
+        [false, true].map(|mts| {
+          let options = TypescriptOptions {
+            import_style,
+            mts,
+            ..Default::default()
+          };
+          format!(
+            "{import_style:?}{}: {} {} {}",
+            if mts { " (mts)" } else { "" },
+            options.file_import("./root"),
+            options.dir_import("./about"),
+            options.dir_import("../.."),
+          )
+        })
+      })
+      .collect::<Vec<_>>();
+
+    insta::assert_snapshot!(specifiers.join("\n"));
+  }
+
+  #[test]
+  fn single_file() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+title = "Messages"
+m = "Taken by the namespace"
+
+[about]
+title = "About"
+more_copyright = "Collides with about.more.copyright"
+
+[about.more]
+copyright = "Copyright {year:number} by {author}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+title = "Nachrichten"
+
+[about]
+title = "Über uns"
+
+[about.more]
+copyright = "Copyright {year:number} von {author}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("de".to_string())];
+    insta::assert_snapshot!(write_single_file(
+      &locales,
+      &module,
+      &TypescriptOptions::default()
+    ));
+  }
+
+  #[test]
+  fn single_file_exports() {
+    let module = module(&[(
+      "en",
+      r#"
+[a]
+b = "Exported as a_b"
+
+[a_b]
+c = "Exported as a_b_c"
+
+[setLocaleFn]
+x = "Collides with the runtime"
+"#,
+    )]);
+
+    let out = write_single_file(
+      &[Locale("en".to_string())],
+      &module,
+      &TypescriptOptions::default(),
+    );
+
+    // Every message and top-level module is a named export, so they can be tree-shaken
+    for export in [
+      "a_b = (",
+      "a_b_c = (",
+      "setLocaleFn_x = (",
+      "a = {",
+      "a_b_ = {",
+      "setLocaleFn_ = {",
+    ] {
+      assert!(
+        out.contains(&format!("\nexport const {export}")),
+        "{export}"
+      );
+    }
+
+    assert!(
+      out.ends_with("export const m = {\n  a,\n  a_b: a_b_,\n  setLocaleFn: setLocaleFn_,\n}\n")
+    );
+  }
+
+  #[test]
+  fn boolean_and_enum_params() {
+    let module = module(&[(
+      "en",
+      r#"
+plan = "{tier:enum(free|pro|team) -> free {Free} *other {Paid}} plan"
+notifications = "Notifications {enabled:boolean -> true {on} *false {off}}"
+shared = "Shared: {shared:boolean}"
+"#,
+    )]);
+
+    insta::assert_snapshot!(write_single_file(
+      &[Locale("en".to_string())],
+      &module,
+      &TypescriptOptions::default()
+    ));
+  }
+
+  #[test]
+  fn custom_type_formatters() {
+    let money = CustomType {
+      name: "money".to_string(),
+      type_name: "Money".to_string(),
+      formatter: "formatMoney".to_string(),
+      module: "./money".to_string(),
+    };
+
+    let date = CustomType {
+      name: "date".to_string(),
+      type_name: "CalendarDate".to_string(),
+      formatter: "formatCalendarDate".to_string(),
+      module: "@app/format".to_string(),
+    };
+
+    let options = BuildOptions {
+      custom_types: vec![money, date],
+      ..Default::default()
+    };
+
+    let module = module_with(
+      &[
+        (
+          "en",
+          r#"
+total = "Total: {total:money}"
+
+[orders]
+placed = "Placed on {date:date} for {total:money}"
+"#,
+        ),
+        (
+          "de",
+          r#"
+total = "Summe: {total:money}"
+"#,
+        ),
+      ],
+      &options,
+    );
+
+    let locales = [Locale("en".to_string()), Locale("de".to_string())];
+    let orders = &module.modules[&Key::new("orders")];
+    let node_next = TypescriptOptions {
+      import_style: ImportStyle::NodeNext,
+      ..Default::default()
+    };
+
+    insta::assert_snapshot!(
+      [
+        write_single_file(&locales, &module, &TypescriptOptions::default()),
+        write_dictionary(&locales[0], 1, orders, &TypescriptOptions::default()),
+        write_dictionary(&locales[1], 1, orders, &TypescriptOptions::default()),
+        write_dictionary(&locales[0], 1, orders, &node_next),
+      ]
+      .join("---\n")
+    );
+  }
+
+  #[test]
+  fn optional_params() {
+    let options = BuildOptions {
+      custom_types: vec![CustomType {
+        name: "money".to_string(),
+        type_name: "Money".to_string(),
+        formatter: "formatMoney".to_string(),
+        module: "./money".to_string(),
+      }],
+      ..Default::default()
+    };
+
+    let module = module_with(
+      &[
+        (
+          "en",
+          r#"
+greeting = "Hello {name=there}{suffix?}"
+total = "Total: {total:money=unknown}"
+signature = "Best, {sender=the team}"
+"#,
+        ),
+        (
+          "de",
+          r#"
+greeting = "Hallo {name=du}{suffix?}"
+total = "Summe: {total=unbekannt}"
+signature = "Viele Grüße, {sender}"
+"#,
+        ),
+      ],
+      &options,
+    );
+
+    insta::assert_snapshot!(write_single_file(
+      &[Locale("en".to_string()), Locale("de".to_string())],
+      &module,
+      &TypescriptOptions::default()
+    ));
+  }
+
+  #[test]
+  fn jsdoc_comments() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+# Shown on the checkout button, max 20 chars
+checkout = "Checkout"
+
+# Separated from the key, so it's ignored
+
+multiline = """
+First line
+Second line with {count:number} and */"""
+
+# Contact details
+contact = { email = "Email us" }
+
+# About page
+[about]
+# Footer, don't remove the */ marker
+copyright = "Copyright {year:number}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+# Auf dem Bezahlen-Button
+checkout = "Zur Kasse"
+only_german = "Nur auf Deutsch"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("de".to_string())];
+    let about = &module.modules[&Key::new("about")];
+
+    insta::assert_snapshot!(
+      [
+        message_jsdoc(&module.messages[&Key::new("checkout")], ""),
+        message_jsdoc(&module.messages[&Key::new("multiline")], ""),
+        message_jsdoc(&module.messages[&Key::new("only_german")], "  "),
+        module_jsdoc(&module.modules[&Key::new("contact")], ""),
+        module_jsdoc(about, "    "),
+        message_jsdoc(&about.messages[&Key::new("copyright")], ""),
+        write_single_file(&locales, &module, &TypescriptOptions::default()),
+      ]
+      .join("---\n")
+    );
+  }
+
+  #[test]
+  fn lazy_loading() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+title = "Messages"
+only_english = "Only in English"
+
+[about.more]
+copyright = "Copyright {year:number} by {author}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+title = "Nachrichten"
+
+[about.more]
+copyright = "Copyright {year:number} von {author}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("de".to_string())];
+    let options = TypescriptOptions {
+      import_style: ImportStyle::NodeNext,
+      lazy: true,
+      ..Default::default()
+    };
+
+    let more = &module.modules[&Key::new("about")].modules[&Key::new("more")];
+    let (copyright_key, copyright) = more.messages.iter().next().unwrap();
+
+    insta::assert_snapshot!(
+      [
+        write_loaders(&locales, &module, &options),
+        write_dictionary(&locales[1], 0, &module, &options),
+        write_dictionary(&locales[1], 2, more, &options),
+        lazy_function_body("about/more", copyright_key, copyright),
+      ]
+      .join("---\n")
+    );
+  }
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{VERSION, format_string};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{DEFAULT_LOCALE, Locale, Message, Module, PLURAL_CATEGORIES, Part};
+
+/// Writes a `strings.xml` resource file per locale into `values-<locale>` directories. The
+/// default locale is written into `values`, which Android uses as the fallback.
+///
+/// Key paths are joined with the separator, and characters that aren't valid in resource names
+/// are replaced with underscores. Existing files in the output directory are kept.
+pub fn generate(
+  dir: &Path,
+  locales: &[Locale],
+  module: &Module,
+  separator: &str,
+) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  for locale in locales {
+    let dir = dir.join(values_dir(locale));
+    fs::create_dir_all(&dir)?;
+    fs::write(
+      dir.join("strings.xml"),
+      write_strings(locale, module, separator),
+    )?;
+  }
+
+  Ok(())
+}
+
+fn write_strings(locale: &Locale, module: &Module, separator: &str) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+  let _ = writeln!(
+    out,
+    "<!-- Code generated by woof v{VERSION}. DO NOT EDIT. -->"
+  );
+  let _ = writeln!(out, "<resources>");
+
+  for (key_path, message) in module.flatten_with(separator) {
+    let Some(parts) = message.parts_for_locale(locale) else {
+      continue;
+    };
+
+    let name = resource_name(&key_path);
+
+    if let Some(items) = plural_items(message, &parts) {
+      let _ = writeln!(out, r#"  <plurals name="{name}">"#);
+      for (quantity, parts) in items {
+        let _ = writeln!(
+          out,
+          r#"    <item quantity="{quantity}">{}</item>"#,
+          escape(&format_string(message, &parts, placeholder))
+        );
+      }
+      let _ = writeln!(out, "  </plurals>");
+      continue;
+    }
+
+    let _ = writeln!(
+      out,
+      r#"  <string name="{name}">{}</string>"#,
+      escape(&format_string(message, &parts, placeholder))
+    );
+  }
+
+  let _ = writeln!(out, "</resources>");
+  out
+}
+
+/// Android resource qualifiers use `-r` for two-letter regions, and BCP 47 tags for everything else
+fn values_dir(locale: &Locale) -> String {
+  if locale.0 == DEFAULT_LOCALE {
+    return "values".to_string();
+  }
+
+  let subtags = locale.0.split(['-', '_']).collect::<Vec<_>>();
+  match subtags.as_slice() {
+    [language] => format!("values-{language}"),
+    [language, region] if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) => {
+      format!("values-{language}-r{}", region.to_uppercase())
+    }
+    _ => format!("values-b+{}", subtags.join("+")),
+  }
+}
+
+/// Resource names have to be valid Java identifiers
+fn resource_name(key_path: &str) -> String {
+  let mut name = key_path
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() || c == '_' {
+        c
+      } else {
+        '_'
+      }
+    })
+    .collect::<String>();
+
+  if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+    name.insert(0, '_');
+  }
+
+  name
+}
+
+fn placeholder(position: usize, type_: &InterpolationType) -> String {
+  match type_ {
+    InterpolationType::Number => format!("%{position}$d"),
+    InterpolationType::Boolean => format!("%{position}$b"),
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => {
+      format!("%{position}$s")
+    }
+  }
+}
+
+/// Messages containing a single select on a number with plural categories as keys can be written
+/// as `<plurals>`, with the default variant as the required `other` quantity
+fn plural_items(message: &Message, parts: &[Part]) -> Option<Vec<(&'static str, Vec<Part>)>> {
+  let mut selects = parts
+    .iter()
+    .enumerate()
+    .filter(|(_, part)| matches!(part, Part::Select { .. }));
+
+  let (index, Part::Select { name, variants }) = selects.next()? else {
+    return None;
+  };
+
+  if selects.next().is_some()
+    || message.interpolations.get(name)?.type_ != InterpolationType::Number
+  {
+    return None;
+  }
+
+  let mut items = BTreeMap::new();
+  for variant in variants {
+    // Quantities can't match exact numbers, so those variants are left out
+    if !variant.default && variant.key.parse::<f64>().is_ok() {
+      continue;
+    }
+
+    let quantity = if variant.default {
+      "other"
+    } else {
+      PLURAL_CATEGORIES
+        .iter()
+        .copied()
+        .find(|category| *category == variant.key)?
+    };
+
+    // The default variant takes precedence over an explicit `other` variant
+    if variant.default || !items.contains_key(quantity) {
+      let mut item = parts[..index].to_vec();
+      item.extend(variant.parts.iter().cloned());
+      item.extend(parts[index + 1..].iter().cloned());
+      items.insert(quantity, item);
+    }
+  }
+
+  Some(
+    PLURAL_CATEGORIES
+      .iter()
+      .filter_map(|category| items.remove(category).map(|item| (*category, item)))
+      .collect(),
+  )
+}
+
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  // References to other resources and theme attributes start with these
+  if s.starts_with(['@', '?']) {
+    result.push('\\');
+  }
+
+  for c in s.chars() {
+    match c {
+      '&' => result.push_str("&amp;"),
+      '<' => result.push_str("&lt;"),
+      '>' => result.push_str("&gt;"),
+      '\\' => result.push_str("\\\\"),
+      '\'' => result.push_str("\\'"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\t' => result.push_str("\\t"),
+      _ => result.push(c),
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn strings_xml() {
+    let module = module(&[(
+      "en",
+      r#"
+greeting = "Hello, {name}! You're <b>{age:number}</b> & 100% \"cool\""
+percent = "100% sure"
+reference = "@string/app_name"
+items = "{count:number -> 0 {No items} one {One item in {place}} *other {{count} items in {place}}}"
+gender = "{gender -> female {She} *other {They}} replied"
+"1st-key" = "First"
+
+[nested.module]
+multiline = """
+First line
+Second line"""
+"#,
+    )]);
+
+    insta::assert_snapshot!(write_strings(&Locale("en".to_string()), &module, "_"));
+  }
+
+  #[test]
+  fn values_dirs() {
+    insta::assert_debug_snapshot!(
+      ["en", "de", "pt-BR", "es_419", "zh-Hant", "zh-Hant-TW"]
+        .map(|locale| values_dir(&Locale(locale.to_string())))
+    );
+  }
+}
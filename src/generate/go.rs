@@ -0,0 +1,557 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{PluralRule, SelectCondition, VERSION, locale_ident, select_conditions};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{DEFAULT_LOCALE, Key, Locale, Message, Module, Part, Variant};
+use crate::sanitize::{go_exported_name, go_package_name, unescape_translation};
+
+static FILENAME: &str = "messages.go";
+
+/// Writes a Go package per top-level module, with messages of nested modules prefixed by their
+/// key path. Messages at the root are written to a package named after the output directory.
+///
+/// Every package declares its own `Locale` type, so packages don't need to import each other.
+/// Every message becomes a function taking the locale, and an argument struct if it has
+/// interpolations.
+pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  if dir.exists() {
+    fs::remove_dir_all(dir)?;
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  if !module.messages.is_empty() {
+    let name = dir
+      .file_name()
+      .map(|name| go_package_name(&name.to_string_lossy()))
+      .unwrap_or_else(|| "messages".to_string());
+
+    let messages = module
+      .messages
+      .iter()
+      .map(|(key, message)| (key.literal.clone(), message))
+      .collect::<Vec<_>>();
+
+    fs::write(
+      dir.join(FILENAME),
+      write_package(&name, &locales, &messages),
+    )?;
+  }
+
+  let mut packages = HashSet::new();
+  for (key, module) in &module.modules {
+    let name = unique(go_package_name(&key.literal), &mut packages);
+    let dir = dir.join(&name);
+    fs::create_dir_all(&dir)?;
+    fs::write(
+      dir.join(FILENAME),
+      write_package(&name, &locales, &module.flatten()),
+    )?;
+  }
+
+  Ok(())
+}
+
+fn write_package(name: &str, locales: &[Locale], messages: &[(String, &Message)]) -> String {
+  let constants = locales
+    .iter()
+    .map(|locale| (format!("Locale{}", locale_ident(locale)), locale))
+    .collect::<Vec<_>>();
+
+  // Names are converted to exported identifiers, so different keys can end up with the same name
+  let mut names = ["Locale", "Locales", "DefaultLocale"]
+    .into_iter()
+    .map(String::from)
+    .chain(constants.iter().map(|(constant, _)| constant.clone()))
+    .collect::<HashSet<_>>();
+
+  let constant_names = constants
+    .iter()
+    .map(|(constant, locale)| (*locale, constant.as_str()))
+    .collect::<HashMap<_, _>>();
+
+  let mut body = String::new();
+  let mut uses_strconv = false;
+  for (path, message) in messages {
+    let name = unique(go_exported_name(path), &mut names);
+    let function = Function::new(message);
+
+    let _ = writeln!(body);
+    if message.interpolations.is_empty() {
+      let _ = writeln!(body, "func {name}(locale Locale) string {{");
+    } else {
+      let args = unique(format!("{name}Args"), &mut names);
+      let width = function.fields.values().map(|f| f.len()).max().unwrap_or(0);
+
+      let _ = writeln!(body, "type {args} struct {{");
+      // Arguments that every translation has a default for can be omitted as nil pointers
+      for (key, interpolation) in &message.interpolations {
+        let pointer = if interpolation.required { "" } else { "*" };
+        let _ = writeln!(
+          body,
+          "\t{:width$} {pointer}{}",
+          function.fields[key],
+          go_type(&interpolation.type_)
+        );
+      }
+      let _ = writeln!(body, "}}");
+      let _ = writeln!(body);
+      let _ = writeln!(body, "func {name}(locale Locale, args {args}) string {{");
+    }
+
+    let _ = writeln!(body, "\tswitch locale {{");
+    for locale in message.translation.keys() {
+      if let Some(parts) = message.parts_for_locale(locale) {
+        let _ = writeln!(body, "\tcase {}:", constant_names[locale]);
+
+        if let [Part::Select { name, variants }] = parts.as_slice() {
+          body.push_str(&function.select(name, variants, 2));
+        } else {
+          let _ = writeln!(body, "\t\treturn {}", function.expression(&parts, 2));
+        }
+      }
+    }
+    let _ = writeln!(body, "\t}}");
+    let _ = writeln!(body, "\treturn {}", string(path));
+    let _ = writeln!(body, "}}");
+
+    uses_strconv |= function.uses_strconv.get();
+  }
+
+  let mut out = String::new();
+  let _ = writeln!(out, "// Code generated by woof v{VERSION}. DO NOT EDIT.");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "package {name}");
+
+  if uses_strconv {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "import \"strconv\"");
+  }
+
+  let width = constants.iter().map(|(c, _)| c.len()).max().unwrap_or(0);
+  let _ = writeln!(out);
+  let _ = writeln!(out, "// Locale is a locale messages can be translated to");
+  let _ = writeln!(out, "type Locale string");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "const (");
+  for (constant, locale) in &constants {
+    let _ = writeln!(out, "\t{constant:width$} Locale = {}", string(&locale.0));
+  }
+  let _ = writeln!(out, ")");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "// DefaultLocale is the locale of the source messages");
+  let _ = writeln!(
+    out,
+    "const DefaultLocale Locale = {}",
+    string(DEFAULT_LOCALE)
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(out, "// Locales contains all locales with translations");
+  let _ = writeln!(
+    out,
+    "var Locales = []Locale{{{}}}",
+    constants
+      .iter()
+      .map(|(constant, _)| constant.as_str())
+      .collect::<Vec<_>>()
+      .join(", ")
+  );
+
+  out.push_str(&body);
+
+  if body.contains("pluralCategory(") {
+    write_plural_category(&mut out, locales, &constant_names);
+  }
+
+  out
+}
+
+fn write_plural_category(
+  out: &mut String,
+  locales: &[Locale],
+  constant_names: &HashMap<&Locale, &str>,
+) {
+  let rules = PluralRule::group(locales);
+
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "func pluralCategory(locale Locale, n float64) string {{"
+  );
+
+  if !rules.is_empty() {
+    let _ = writeln!(out, "\tswitch locale {{");
+    for (rule, locales) in rules {
+      let locales = locales
+        .iter()
+        .map(|locale| constant_names[locale])
+        .collect::<Vec<_>>()
+        .join(", ");
+
+      let _ = writeln!(out, "\tcase {locales}:");
+      for line in plural_rule(rule) {
+        let _ = writeln!(out, "\t\t{line}");
+      }
+    }
+    let _ = writeln!(out, "\t}}");
+  }
+
+  for line in plural_rule(PluralRule::One) {
+    let _ = writeln!(out, "\t{line}");
+  }
+  let _ = writeln!(out, "}}");
+}
+
+/// The body of a message function, with the names of its argument struct fields
+struct Function<'a> {
+  message: &'a Message,
+  fields: HashMap<&'a Key, String>,
+  /// Whether numbers or booleans were formatted, which needs the `strconv` import
+  uses_strconv: Cell<bool>,
+}
+
+impl<'a> Function<'a> {
+  fn new(message: &'a Message) -> Self {
+    let mut names = HashSet::new();
+    let fields = message
+      .interpolations
+      .keys()
+      .map(|key| (key, unique(go_exported_name(&key.literal), &mut names)))
+      .collect();
+
+    Self {
+      message,
+      fields,
+      uses_strconv: Cell::new(false),
+    }
+  }
+
+  /// Renders parts as an expression evaluating to a `string`, concatenating its pieces. Selects
+  /// within other parts and optional arguments become closures, indented by the given number of
+  /// tabs.
+  fn expression(&self, parts: &[Part], indent: usize) -> String {
+    if parts.is_empty() {
+      return "\"\"".to_string();
+    }
+
+    parts
+      .iter()
+      .map(|part| match part {
+        Part::Text(text) => string(&unescape_translation(text)),
+        Part::Interpolation {
+          name: key,
+          default: Some(default),
+        } if !self.message.interpolations[key].required => {
+          let tabs = "\t".repeat(indent);
+          let field = format!("args.{}", self.fields[key]);
+          format!(
+            "func() string {{\n{tabs}\tif {field} != nil {{\n{tabs}\t\treturn {}\n{tabs}\t}}\n{tabs}\treturn {}\n{tabs}}}()",
+            self.format(key, format!("*{field}")),
+            string(&unescape_translation(default))
+          )
+        }
+        Part::Interpolation { name: key, .. } => {
+          self.format(key, format!("args.{}", self.fields[key]))
+        }
+        Part::Select { name, variants } => format!(
+          "func() string {{\n{}{}}}()",
+          self.select(name, variants, indent + 1),
+          "\t".repeat(indent)
+        ),
+      })
+      .collect::<Vec<_>>()
+      .join(" + ")
+  }
+
+  /// Renders a select as a chain of if statements returning the variants, one per
+  /// [`select_conditions`]
+  fn select(&self, name: &Key, variants: &[Variant], indent: usize) -> String {
+    let field = format!("args.{}", self.fields[name]);
+    let conditions = select_conditions(self.message, name, variants)
+      .into_iter()
+      .map(|(condition, variant)| {
+        let condition = match condition {
+          SelectCondition::Boolean(true) => field.clone(),
+          SelectCondition::Boolean(false) => format!("!{field}"),
+          SelectCondition::Equals(key) => format!("{field} == {}", string(key)),
+          SelectCondition::Number(number) => format!("{field} == {number}"),
+          SelectCondition::PluralCategory(key) => {
+            format!("pluralCategory(locale, {field}) == {}", string(key))
+          }
+        };
+
+        (condition, variant)
+      })
+      .collect::<Vec<_>>();
+
+    let tabs = "\t".repeat(indent);
+    let mut out = String::new();
+
+    for (condition, variant) in conditions {
+      let _ = writeln!(out, "{tabs}if {condition} {{");
+      let _ = writeln!(
+        out,
+        "{tabs}\treturn {}",
+        self.expression(&variant.parts, indent + 1)
+      );
+      let _ = writeln!(out, "{tabs}}}");
+    }
+
+    let default = variants
+      .iter()
+      .find(|v| v.default)
+      .map(|v| self.expression(&v.parts, indent))
+      .unwrap_or_else(|| "\"\"".to_string());
+
+    let _ = writeln!(out, "{tabs}return {default}");
+    out
+  }
+
+  /// Formats the value of an argument as a string
+  fn format(&self, key: &Key, field: String) -> String {
+    match self.message.interpolations[key].type_ {
+      InterpolationType::None
+      | InterpolationType::String
+      | InterpolationType::Enum(_)
+      | InterpolationType::Custom(_) => field,
+      InterpolationType::Number => {
+        self.uses_strconv.set(true);
+        format!("strconv.FormatFloat({field}, 'f', -1, 64)")
+      }
+      InterpolationType::Boolean => {
+        self.uses_strconv.set(true);
+        format!("strconv.FormatBool({field})")
+      }
+    }
+  }
+}
+
+/// The body of `pluralCategory` for a plural rule
+fn plural_rule(rule: PluralRule) -> &'static [&'static str] {
+  match rule {
+    PluralRule::Other => &["return \"other\""],
+    PluralRule::One => &["if n == 1 {", "\treturn \"one\"", "}", "return \"other\""],
+    PluralRule::ZeroOne => &[
+      "if n > -2 && n < 2 {",
+      "\treturn \"one\"",
+      "}",
+      "return \"other\"",
+    ],
+    PluralRule::EastSlavic => &[
+      "if n != float64(int64(n)) {",
+      "\treturn \"other\"",
+      "}",
+      "i := int64(n)",
+      "if i < 0 {",
+      "\ti = -i",
+      "}",
+      "if i%10 == 1 && i%100 != 11 {",
+      "\treturn \"one\"",
+      "}",
+      "if i%10 >= 2 && i%10 <= 4 && (i%100 < 12 || i%100 > 14) {",
+      "\treturn \"few\"",
+      "}",
+      "return \"many\"",
+    ],
+    PluralRule::Polish => &[
+      "if n != float64(int64(n)) {",
+      "\treturn \"other\"",
+      "}",
+      "i := int64(n)",
+      "if i < 0 {",
+      "\ti = -i",
+      "}",
+      "if i == 1 {",
+      "\treturn \"one\"",
+      "}",
+      "if i%10 >= 2 && i%10 <= 4 && (i%100 < 12 || i%100 > 14) {",
+      "\treturn \"few\"",
+      "}",
+      "return \"many\"",
+    ],
+    PluralRule::Czech => &[
+      "if n != float64(int64(n)) {",
+      "\treturn \"many\"",
+      "}",
+      "if n == 1 {",
+      "\treturn \"one\"",
+      "}",
+      "if n >= 2 && n <= 4 {",
+      "\treturn \"few\"",
+      "}",
+      "return \"other\"",
+    ],
+    PluralRule::Arabic => &[
+      "if n != float64(int64(n)) {",
+      "\treturn \"other\"",
+      "}",
+      "i := int64(n)",
+      "if i < 0 {",
+      "\ti = -i",
+      "}",
+      "switch {",
+      "case i <= 2:",
+      "\treturn [...]string{\"zero\", \"one\", \"two\"}[i]",
+      "case i%100 >= 3 && i%100 <= 10:",
+      "\treturn \"few\"",
+      "case i%100 >= 11:",
+      "\treturn \"many\"",
+      "}",
+      "return \"other\"",
+    ],
+  }
+}
+
+fn go_type(type_: &InterpolationType) -> &'static str {
+  match type_ {
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => "string",
+    InterpolationType::Number => "float64",
+    InterpolationType::Boolean => "bool",
+  }
+}
+
+/// Appends underscores to a name until it's not used yet, and marks it as used
+fn unique(mut name: String, used: &mut HashSet<String>) -> String {
+  while used.contains(&name) {
+    name.push('_');
+  }
+
+  used.insert(name.clone());
+  name
+}
+
+/// Quotes a string as a Go string literal
+fn string(s: &str) -> String {
+  let mut result = String::with_capacity(s.len() + 2);
+  result.push('"');
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(result, "\\u{:04x}", c as u32);
+      }
+      c => result.push(c),
+    }
+  }
+
+  result.push('"');
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn go_package() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! {{Braces} and \"quotes\""
+locale = "English"
+
+[notifications]
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *other {They}} replied to {user_name}"
+new_messages = "New messages"
+"new-messages" = "Also new messages"
+
+[notifications.storage]
+usage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "ru",
+        r#"
+greeting = "Привет, {name}!"
+
+[notifications]
+items = "{count:number -> one {{count} элемент} few {{count} элемента} *other {{count} элементов}}"
+reply = "{gender -> female {Она} *other {Они}} ответили {user_name}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("ru".to_string())];
+    let root = module
+      .messages
+      .iter()
+      .map(|(key, message)| (key.literal.clone(), message))
+      .collect::<Vec<_>>();
+
+    let notifications = &module.modules[&Key::new("notifications")];
+
+    insta::assert_snapshot!(format!(
+      "{}\n---\n{}",
+      write_package("messages", &locales, &root),
+      write_package("notifications", &locales, &notifications.flatten())
+    ));
+  }
+
+  #[test]
+  fn strconv_import() {
+    let module = module(&[(
+      "en",
+      r#"
+hint = "Use strconv.Itoa for {name}"
+count = "{count:number} items"
+"#,
+    )]);
+
+    let locales = [Locale("en".to_string())];
+    let messages = module.flatten();
+    let (count, hint) = messages.split_at(1);
+
+    assert!(write_package("messages", &locales, count).contains("import \"strconv\""));
+    assert!(!write_package("messages", &locales, hint).contains("import \"strconv\""));
+  }
+
+  #[test]
+  fn optional_args() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+items = "{count:number=No} items"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo {name=du}{suffix?}"
+items = "{count:number=Keine} Einträge"
+signature = "Viele Grüße, {sender}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("de".to_string()), Locale("en".to_string())];
+    insta::assert_snapshot!(write_package("messages", &locales, &module.flatten()));
+  }
+}
@@ -0,0 +1,109 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{VERSION, format_string};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{Locale, Module};
+
+/// Writes a `Localizable.strings` file per locale into `<locale>.lproj` directories.
+///
+/// Key paths are joined with the separator. Existing files in the output directory are kept.
+pub fn generate(
+  dir: &Path,
+  locales: &[Locale],
+  module: &Module,
+  separator: &str,
+) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  for locale in locales {
+    let dir = dir.join(format!("{locale}.lproj"));
+    fs::create_dir_all(&dir)?;
+    fs::write(
+      dir.join("Localizable.strings"),
+      write_strings(locale, module, separator),
+    )?;
+  }
+
+  Ok(())
+}
+
+fn write_strings(locale: &Locale, module: &Module, separator: &str) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "/* Code generated by woof v{VERSION}. DO NOT EDIT. */");
+
+  for (key_path, message) in module.flatten_with(separator) {
+    let Some(parts) = message.parts_for_locale(locale) else {
+      continue;
+    };
+
+    let _ = writeln!(
+      out,
+      r#""{}" = "{}";"#,
+      escape(&key_path),
+      escape(&format_string(message, &parts, placeholder))
+    );
+  }
+
+  out
+}
+
+fn placeholder(position: usize, type_: &InterpolationType) -> String {
+  match type_ {
+    InterpolationType::Number => format!("%{position}$ld"),
+    // Booleans aren't supported by format strings, so they're passed as strings like enums
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Boolean
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => format!("%{position}$@"),
+  }
+}
+
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      _ => result.push(c),
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn localizable_strings() {
+    let module = module(&[(
+      "de",
+      r#"
+greeting = "Hallo, \"{name}\"! Du bist {age:number} und zu 100% \\ cool"
+percent = "100% sicher"
+items = "{count:number -> 0 {Keine} one {Ein Element} *other {{count} Elemente}}"
+
+[nested."key \"quoted\""]
+multiline = """
+Erste Zeile
+Zweite Zeile"""
+"#,
+    )]);
+
+    insta::assert_snapshot!(write_strings(&Locale("de".to_string()), &module, "."));
+  }
+}
@@ -0,0 +1,253 @@
+use std::fs;
+use std::iter::repeat_n;
+use std::path::Path;
+
+use super::{
+  CustomImports, VERSION, custom_type_imports, function_body, locales_union, message_jsdoc,
+  module_jsdoc, typed_params,
+};
+use crate::errors::WoofError;
+use crate::parse::{DEFAULT_LOCALE, Key, Locale, Module};
+
+static HEADER: &str = "// eslint-disable\n// biome-ignore-all: generated code";
+
+/// Writes ES modules mirroring the messages into the given directory, replacing its previous
+/// contents. Every `.js` file has a `.d.ts` file next to it declaring the same types as the
+/// TypeScript output, so the messages can be published as a package without a build step.
+pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  if dir.exists() {
+    fs::remove_dir_all(dir)?;
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let locales = locales_union(locales);
+  fs::write(dir.join("index.js"), write_index())?;
+  fs::write(dir.join("index.d.ts"), write_index_declarations(&locales))?;
+
+  write_package(dir, 0, module, &locales)
+}
+
+fn write_package(
+  dir: &Path,
+  depth: usize,
+  module: &Module,
+  locales: &str,
+) -> Result<(), WoofError> {
+  if module.messages.is_empty() && module.modules.is_empty() {
+    return Ok(());
+  }
+
+  let filename = if depth == 0 { "root" } else { "index" };
+  fs::write(
+    dir.join(format!("{filename}.js")),
+    write_module(depth, module),
+  )?;
+  fs::write(
+    dir.join(format!("{filename}.d.ts")),
+    write_declarations(depth, module, locales),
+  )?;
+
+  for (key, module) in &module.modules {
+    let dir = dir.join(&key.sanitized);
+    fs::create_dir_all(&dir)?;
+    write_package(&dir, depth + 1, module, locales)?;
+  }
+
+  Ok(())
+}
+
+fn write_index() -> String {
+  format!(
+    r#"// Code generated by woof v{VERSION}. DO NOT EDIT.
+{HEADER}
+export const defaultLocale = "{DEFAULT_LOCALE}"
+export let getLocale = () => "{DEFAULT_LOCALE}"
+export const setLocaleFn = (fn) => (getLocale = fn)
+export * as m from "./root.js"
+"#
+  )
+}
+
+fn write_index_declarations(locales: &str) -> String {
+  format!(
+    r#"// Code generated by woof v{VERSION}. DO NOT EDIT.
+{HEADER}
+export type Locale = {locales}
+export declare const defaultLocale = "{DEFAULT_LOCALE}"
+export declare let getLocale: () => Locale
+export declare const setLocaleFn: (fn: () => Locale) => () => Locale
+export * as m from "./root.js"
+"#
+  )
+}
+
+/// Writes the functions of a module. Unlike in TypeScript, imports need file extensions to be
+/// resolved by Node.js and browsers.
+fn write_module(depth: usize, module: &Module) -> String {
+  let mut out = format!("// Code generated by woof v{VERSION}. DO NOT EDIT.\n{HEADER}\n");
+
+  if !module.messages.is_empty() {
+    let root_import = if depth == 0 {
+      "./index.js".to_string()
+    } else {
+      format!(
+        "{}/index.js",
+        repeat_n("..", depth).collect::<Vec<_>>().join("/")
+      )
+    };
+
+    out.push_str(&format!("import {{ getLocale }} from \"{root_import}\"\n"));
+    out.push_str(&custom_type_imports(
+      module.messages.values(),
+      depth,
+      CustomImports::Formatters,
+      js_import,
+    ));
+  }
+
+  for (key, message) in &module.messages {
+    let params = if message.interpolations.is_empty() {
+      "locale"
+    } else {
+      "args, locale"
+    };
+
+    out.push_str(&format!(
+      "export const {key} = ({params}) => {{\n",
+      key = key.sanitized
+    ));
+    out.push_str(&function_body(key, message));
+  }
+
+  for key in module.modules.keys() {
+    write_reexport(&mut out, key);
+  }
+
+  out
+}
+
+fn write_declarations(depth: usize, module: &Module, locales: &str) -> String {
+  let mut out = format!("// Code generated by woof v{VERSION}. DO NOT EDIT.\n{HEADER}\n");
+  out.push_str(&custom_type_imports(
+    module.messages.values(),
+    depth,
+    CustomImports::Types,
+    js_import,
+  ));
+
+  for (key, message) in &module.messages {
+    out.push_str(&message_jsdoc(message, ""));
+    out.push_str(&format!(
+      "export declare const {key}: ({params}) => string\n",
+      key = key.sanitized,
+      params = typed_params(message, locales)
+    ));
+  }
+
+  for (key, module) in &module.modules {
+    out.push_str(&module_jsdoc(module, ""));
+    write_reexport(&mut out, key);
+  }
+
+  out
+}
+
+/// Specifier for importing a relative module without an extension, which Node.js and browsers
+/// don't resolve
+fn js_import(path: &str) -> String {
+  format!("{path}.js")
+}
+
+fn write_reexport(out: &mut String, key: &Key) {
+  out.push_str(&format!(
+    "export * as {name} from \"./{name}/index.js\"\n",
+    name = key.sanitized
+  ));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::{module, module_with};
+  use crate::interpolations::CustomType;
+  use crate::parse::BuildOptions;
+
+  #[test]
+  fn javascript_modules() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! `{{Braces}` and ${dollars}"
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+only_english = "Only in English"
+
+[nested.module]
+storage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo, {name}!"
+items = "{count:number -> 0 {Keine Einträge} one {Ein Eintrag} *other {{count} Einträge}}"
+
+[nested.module]
+storage = "{used:number} von {total:number}"
+"#,
+      ),
+    ]);
+
+    let locales = locales_union(&[Locale("en".to_string()), Locale("de".to_string())]);
+    let nested = &module.modules[&Key::new("nested")];
+    let nested_module = &nested.modules[&Key::new("module")];
+
+    insta::assert_snapshot!(
+      [
+        write_index(),
+        write_index_declarations(&locales),
+        write_module(0, &module),
+        write_declarations(0, &module, &locales),
+        write_module(1, nested),
+        write_module(2, nested_module),
+        write_declarations(2, nested_module, &locales),
+      ]
+      .join("---\n")
+    );
+  }
+
+  #[test]
+  fn custom_type_extensions() {
+    let options = BuildOptions {
+      custom_types: vec![CustomType {
+        name: "money".to_string(),
+        type_name: "Money".to_string(),
+        formatter: "formatMoney".to_string(),
+        module: "./money".to_string(),
+      }],
+      ..Default::default()
+    };
+
+    let module = module_with(
+      &[("en", r#"orders.total = "Total: {total:money}""#)],
+      &options,
+    );
+    let orders = &module.modules[&Key::new("orders")];
+    let locales = locales_union(&[Locale("en".to_string())]);
+
+    insta::assert_snapshot!(
+      [
+        write_module(1, orders),
+        write_declarations(1, orders, &locales),
+      ]
+      .join("---\n")
+    );
+  }
+}
@@ -0,0 +1,486 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{
+  Namespaces, PluralRule, SelectCondition, VERSION, initial_locale, select_conditions,
+  write_namespaces,
+};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{Key, Locale, Message, Module, Part, Variant};
+use crate::sanitize::{escape_keyword, unescape_translation};
+
+static KEYWORDS: &[&str] = &[
+  "as",
+  "break",
+  "class",
+  "continue",
+  "do",
+  "else",
+  "false",
+  "for",
+  "fun",
+  "if",
+  "in",
+  "interface",
+  "is",
+  "null",
+  "object",
+  "package",
+  "return",
+  "super",
+  "this",
+  "throw",
+  "true",
+  "try",
+  "typealias",
+  "typeof",
+  "val",
+  "var",
+  "when",
+  "while",
+  // Declared in the root object, or used by every message function
+  "m",
+  "Locale",
+  "defaultLocale",
+  "localeFn",
+  "getLocale",
+  "setLocaleFn",
+  "formatNumber",
+  "pluralCategory",
+  "locale",
+];
+
+static FILENAME: &str = "Messages.kt";
+
+/// Writes a `Messages.kt` file with an object `m` mirroring the messages, with a nested object
+/// per module.
+///
+/// Every message becomes a function taking its interpolations and an optional locale, which
+/// defaults to the locale returned by the function set with `m.setLocaleFn`. Other files in the
+/// output directory are kept.
+pub fn generate(
+  dir: &Path,
+  locales: &[Locale],
+  module: &Module,
+  package: Option<&str>,
+) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  fs::write(dir.join(FILENAME), write_file(&locales, module, package))?;
+  Ok(())
+}
+
+fn write_file(locales: &[Locale], module: &Module, package: Option<&str>) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "// Code generated by woof v{VERSION}. DO NOT EDIT.");
+  let _ = writeln!(
+    out,
+    "@file:Suppress(\"ClassName\", \"FunctionName\", \"ObjectPropertyName\", \"unused\")"
+  );
+
+  if let Some(package) = package {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "package {package}");
+  }
+
+  let _ = writeln!(out);
+  let _ = writeln!(out, "object m {{");
+  let _ = writeln!(out, "    enum class Locale(val tag: String) {{");
+  for (i, locale) in locales.iter().enumerate() {
+    let separator = if i + 1 == locales.len() { ";" } else { "," };
+    let _ = writeln!(
+      out,
+      "        {}({}){separator}",
+      constant(locale),
+      string(&locale.0)
+    );
+  }
+  let _ = writeln!(out);
+  let _ = writeln!(out, "        companion object {{");
+  let _ = writeln!(
+    out,
+    "            fun fromTag(tag: String): Locale? = values().find {{ it.tag == tag }}"
+  );
+  let _ = writeln!(out, "        }}");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+
+  let default = initial_locale(locales)
+    .map(|locale| format!("Locale.{}", constant(locale)))
+    .unwrap_or_else(|| "Locale.values().first()".to_string());
+
+  let _ = writeln!(out, "    val defaultLocale: Locale = {default}");
+  let _ = writeln!(
+    out,
+    "    private var localeFn: () -> Locale = {{ defaultLocale }}"
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(out, "    fun getLocale(): Locale = localeFn()");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "    fun setLocaleFn(fn: () -> Locale) {{");
+  let _ = writeln!(out, "        localeFn = fn");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "    fun formatNumber(n: Number): String {{");
+  let _ = writeln!(out, "        val d = n.toDouble()");
+  let _ = writeln!(
+    out,
+    "        return if (d % 1.0 == 0.0 && kotlin.math.abs(d) < 1e15) d.toLong().toString() else d.toString()"
+  );
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+  write_plural_category(&mut out, locales);
+
+  write_namespaces(&mut out, 1, module, &Kotlin { locales });
+  let _ = writeln!(out, "}}");
+  out
+}
+
+fn write_plural_category(out: &mut String, locales: &[Locale]) {
+  let rules = PluralRule::group(locales);
+
+  let _ = writeln!(
+    out,
+    "    fun pluralCategory(locale: Locale, n: Double): String {{"
+  );
+
+  // Locales with the English rules use the fallback at the end
+  for (rule, locales) in rules {
+    let condition = locales
+      .iter()
+      .map(|locale| format!("locale == Locale.{}", constant(locale)))
+      .collect::<Vec<_>>()
+      .join(" || ");
+
+    let _ = writeln!(out, "        if ({condition}) {{");
+    for line in plural_rule(rule) {
+      let _ = writeln!(out, "            {line}");
+    }
+    let _ = writeln!(out, "        }}");
+  }
+
+  for line in plural_rule(PluralRule::One) {
+    let _ = writeln!(out, "        {line}");
+  }
+  let _ = writeln!(out, "    }}");
+}
+
+struct Kotlin<'a> {
+  locales: &'a [Locale],
+}
+
+impl Namespaces for Kotlin<'_> {
+  fn write_message(&self, out: &mut String, key: &Key, message: &Message, indent: &str) {
+    let _ = write!(out, "{indent}fun {}(", ident(key));
+
+    // Arguments that every translation has a default for can be omitted
+    for (key, interpolation) in &message.interpolations {
+      let type_ = kotlin_type(&interpolation.type_);
+      if interpolation.required {
+        let _ = write!(out, "{}: {type_}, ", ident(key));
+      } else {
+        let _ = write!(out, "{}: {type_}? = null, ", ident(key));
+      }
+    }
+
+    let _ = writeln!(
+      out,
+      "locale: m.Locale = m.getLocale()): String = when (locale) {{"
+    );
+
+    for locale in message.translation.keys() {
+      if let Some(parts) = message.parts_for_locale(locale) {
+        let _ = writeln!(
+          out,
+          "{indent}    m.Locale.{} -> {}",
+          constant(locale),
+          expression(message, &parts)
+        );
+      }
+    }
+
+    if self
+      .locales
+      .iter()
+      .any(|locale| !message.translation.contains_key(locale))
+    {
+      let _ = writeln!(out, "{indent}    else -> {}", string(&key.sanitized));
+    }
+
+    let _ = writeln!(out, "{indent}}}");
+  }
+
+  fn open_namespace(&self, out: &mut String, key: &Key, indent: &str) {
+    let _ = writeln!(out, "{indent}object {} {{", ident(key));
+  }
+
+  fn close_namespace(&self, out: &mut String, indent: &str) {
+    let _ = writeln!(out, "{indent}}}");
+  }
+}
+
+/// Renders parts as a string template, with selects as `if` expressions
+fn expression(message: &Message, parts: &[Part]) -> String {
+  if let [Part::Select { name, variants }] = parts {
+    return select(message, name, variants);
+  }
+
+  let mut out = String::from("\"");
+
+  for part in parts {
+    match part {
+      Part::Text(text) => out.push_str(&escape(&unescape_translation(text))),
+      Part::Interpolation {
+        name: key,
+        default: Some(default),
+      } if !message.interpolations[key].required => {
+        let arg = ident(key);
+        let value = match message.interpolations[key].type_ {
+          InterpolationType::Number => format!("{arg}?.let {{ m.formatNumber(it) }}"),
+          _ => arg,
+        };
+
+        let default = string(&unescape_translation(default));
+        let _ = write!(out, "${{{value} ?: {default}}}");
+      }
+      Part::Interpolation { name: key, .. } => match message.interpolations[key].type_ {
+        InterpolationType::Number => {
+          let _ = write!(out, "${{m.formatNumber({})}}", ident(key));
+        }
+        InterpolationType::None
+        | InterpolationType::String
+        | InterpolationType::Boolean
+        | InterpolationType::Enum(_)
+        | InterpolationType::Custom(_) => {
+          let _ = write!(out, "${{{}}}", ident(key));
+        }
+      },
+      Part::Select { name, variants } => {
+        let _ = write!(out, "${{{}}}", select(message, name, variants));
+      }
+    }
+  }
+
+  out.push('"');
+  out
+}
+
+/// Renders a select as a chain of `if` expressions of its [`select_conditions`]
+fn select(message: &Message, name: &Key, variants: &[Variant]) -> String {
+  let arg = ident(name);
+  let conditions = select_conditions(message, name, variants)
+    .into_iter()
+    .map(|(condition, variant)| {
+      let condition = match condition {
+        SelectCondition::Boolean(true) => arg.clone(),
+        SelectCondition::Boolean(false) => format!("!{arg}"),
+        SelectCondition::Equals(key) => format!("{arg} == {}", string(key)),
+        SelectCondition::Number(number) => format!("{arg}.toDouble() == {number:?}"),
+        SelectCondition::PluralCategory(key) => format!(
+          "m.pluralCategory(locale, {arg}.toDouble()) == {}",
+          string(key)
+        ),
+      };
+
+      (condition, variant)
+    })
+    .collect::<Vec<_>>();
+
+  let default = variants
+    .iter()
+    .find(|v| v.default)
+    .map(|v| expression(message, &v.parts))
+    .unwrap_or_else(|| "\"\"".to_string());
+
+  let mut out = String::new();
+  for (condition, variant) in conditions {
+    let _ = write!(
+      out,
+      "if ({condition}) {} else ",
+      expression(message, &variant.parts)
+    );
+  }
+
+  out.push_str(&default);
+  out
+}
+
+/// The body of `pluralCategory` for a plural rule
+fn plural_rule(rule: PluralRule) -> &'static [&'static str] {
+  match rule {
+    PluralRule::Other => &["return \"other\""],
+    PluralRule::One => &["return if (n == 1.0) \"one\" else \"other\""],
+    PluralRule::ZeroOne => &["return if (kotlin.math.abs(n) < 2.0) \"one\" else \"other\""],
+    PluralRule::EastSlavic => &[
+      "if (n % 1.0 != 0.0) return \"other\"",
+      "val i = kotlin.math.abs(n).toLong()",
+      "if (i % 10 == 1L && i % 100 != 11L) return \"one\"",
+      "if (i % 10 in 2L..4L && i % 100 !in 12L..14L) return \"few\"",
+      "return \"many\"",
+    ],
+    PluralRule::Polish => &[
+      "if (n % 1.0 != 0.0) return \"other\"",
+      "val i = kotlin.math.abs(n).toLong()",
+      "if (i == 1L) return \"one\"",
+      "if (i % 10 in 2L..4L && i % 100 !in 12L..14L) return \"few\"",
+      "return \"many\"",
+    ],
+    PluralRule::Czech => &[
+      "if (n % 1.0 != 0.0) return \"many\"",
+      "if (n == 1.0) return \"one\"",
+      "if (n in 2.0..4.0) return \"few\"",
+      "return \"other\"",
+    ],
+    PluralRule::Arabic => &[
+      "if (n % 1.0 != 0.0) return \"other\"",
+      "val i = kotlin.math.abs(n).toLong()",
+      "return when {",
+      "    i == 0L -> \"zero\"",
+      "    i == 1L -> \"one\"",
+      "    i == 2L -> \"two\"",
+      "    i % 100 in 3L..10L -> \"few\"",
+      "    i % 100 >= 11L -> \"many\"",
+      "    else -> \"other\"",
+      "}",
+    ],
+  }
+}
+
+fn kotlin_type(type_: &InterpolationType) -> &'static str {
+  match type_ {
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => "String",
+    InterpolationType::Number => "Number",
+    InterpolationType::Boolean => "Boolean",
+  }
+}
+
+fn ident(key: &Key) -> String {
+  escape_keyword(&key.sanitized, KEYWORDS)
+}
+
+/// Converts a locale like `pt-BR` into an enum constant like `PT_BR`
+fn constant(locale: &Locale) -> String {
+  let mut name = locale
+    .0
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() {
+        c.to_ascii_uppercase()
+      } else {
+        '_'
+      }
+    })
+    .collect::<String>();
+
+  if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+    name.insert(0, 'L');
+  }
+
+  name
+}
+
+/// Escapes text for a Kotlin string template
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '$' => result.push_str("\\$"),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(result, "\\u{:04x}", c as u32);
+      }
+      c => result.push(c),
+    }
+  }
+
+  result
+}
+
+/// Quotes a string as a Kotlin string literal
+fn string(s: &str) -> String {
+  format!("\"{}\"", escape(s))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn kotlin_object() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! {{Braces} and \"quotes\" for $5"
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *other {They}} replied to {object}"
+only_english = "Only in English"
+
+[nested.module]
+storage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "pt-BR",
+        r#"
+greeting = "Olá, {name}!"
+items = "{count:number -> one {Um item} *other {{count} itens}}"
+reply = "{gender -> female {Ela} *other {Eles}} responderam {object}"
+
+[nested.module]
+storage = "{used:number} de {total:number}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    insta::assert_snapshot!(write_file(&locales, &module, Some("com.example.messages")));
+  }
+
+  #[test]
+  fn optional_args() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+items = "{count:number=No} items"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo {name=du}{suffix?}"
+items = "{count:number=Keine} Einträge"
+signature = "Viele Grüße, {sender}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("de".to_string()), Locale("en".to_string())];
+    let mut out = String::new();
+    write_namespaces(&mut out, 0, &module, &Kotlin { locales: &locales });
+    insta::assert_snapshot!(out);
+  }
+}
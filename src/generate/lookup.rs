@@ -0,0 +1,251 @@
+use super::function_body;
+use crate::parse::{Key, Locale, Message, Module};
+
+/// How a message function picks the translation for the resolved locale
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lookup {
+  /// An `if` statement per locale, as generated by default
+  IfChain,
+  /// A `switch` statement, with locales sharing a translation falling through to the same case
+  Switch,
+  /// An object literal keyed by locale, for messages without interpolations
+  Record,
+}
+
+/// Estimated size of all message functions after minification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeEstimate {
+  pub if_chains: usize,
+  pub lookup_tables: usize,
+}
+
+/// Translations of a message grouped by their template, in the order of the first locale using
+/// each template
+fn template_groups(message: &Message) -> Vec<(Vec<&Locale>, String)> {
+  let mut groups: Vec<(Vec<&Locale>, String)> = Vec::new();
+
+  for locale in message.translation.keys() {
+    let Some(template) = message.template_for_locale(locale) else {
+      continue;
+    };
+
+    match groups.iter_mut().find(|(_, t)| *t == template) {
+      Some((locales, _)) => locales.push(locale),
+      None => groups.push((vec![locale], template)),
+    }
+  }
+
+  groups
+}
+
+/// Minifiers only keep the quotes of keys that aren't valid identifiers
+fn minified_property(locale: &Locale) -> String {
+  if locale
+    .0
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && !locale.0.starts_with(|c: char| c.is_ascii_digit())
+  {
+    locale.0.clone()
+  } else {
+    format!("\"{locale}\"")
+  }
+}
+
+/// Whether a template used by several locales is smaller when it's declared once and referenced
+/// by a variable in a record, including the declaration's `const`
+fn share_template(uses: usize, template: &str) -> bool {
+  let inline = template.len() + 2;
+  uses > 1 && (uses - 1) * inline > 2 * uses + 10
+}
+
+/// Renders a function body the way a minifier would, with single letter names for the locale
+/// parameter, `getLocale` and local variables. Templates are kept as they are, since they're the
+/// same for every form.
+fn minified(lookup: Lookup, key: &Key, groups: &[(Vec<&Locale>, String)]) -> String {
+  let fallback = format!("`{}`", key.sanitized);
+
+  match lookup {
+    Lookup::IfChain => {
+      let mut out = String::from("const r=l??g();");
+      for (locales, template) in groups {
+        for locale in locales {
+          out.push_str(&format!("if(r===\"{locale}\")return`{template}`;"));
+        }
+      }
+
+      out + "return" + &fallback
+    }
+    Lookup::Switch => {
+      let mut out = String::from("switch(l??g()){");
+      for (locales, template) in groups {
+        for locale in locales {
+          out.push_str(&format!("case\"{locale}\":"));
+        }
+
+        out.push_str(&format!("return`{template}`;"));
+      }
+
+      out + "}return" + &fallback
+    }
+    Lookup::Record => {
+      let mut shared = Vec::new();
+      let mut entries = Vec::new();
+
+      for (locales, template) in groups {
+        let value = if share_template(locales.len(), template) {
+          shared.push(format!("t{}=`{template}`", shared.len()));
+          format!("t{}", shared.len() - 1)
+        } else {
+          format!("`{template}`")
+        };
+
+        for locale in locales {
+          entries.push(format!("{}:{value}", minified_property(locale)));
+        }
+      }
+
+      let declarations = if shared.is_empty() {
+        String::new()
+      } else {
+        format!("const {};", shared.join(","))
+      };
+
+      format!(
+        "{declarations}return{{{}}}[l??g()]??{fallback}",
+        entries.join(",")
+      )
+    }
+  }
+}
+
+/// Picks the form of a message function that is the smallest after minification
+fn smallest(key: &Key, message: &Message, groups: &[(Vec<&Locale>, String)]) -> Lookup {
+  let mut candidates = vec![Lookup::IfChain, Lookup::Switch];
+
+  // Every entry of a record is evaluated, which would format all translations on every call
+  if message.interpolations.is_empty() {
+    candidates.push(Lookup::Record);
+  }
+
+  candidates
+    .into_iter()
+    .min_by_key(|lookup| minified(*lookup, key, groups).len())
+    .unwrap_or(Lookup::IfChain)
+}
+
+/// Body of a message function using the smallest form after minification. Translations that are
+/// shared by several locales are only included once.
+pub(super) fn lookup_function_body(key: &Key, message: &Message) -> String {
+  let groups = template_groups(message);
+
+  match smallest(key, message, &groups) {
+    Lookup::IfChain => function_body(key, message),
+    Lookup::Switch => {
+      let mut body = String::from("  switch (locale ?? getLocale()) {\n");
+      for (locales, template) in &groups {
+        for locale in locales {
+          body.push_str(&format!("    case \"{locale}\":\n"));
+        }
+
+        body.push_str(&format!("      return `{template}`\n"));
+      }
+
+      body.push_str(&format!("  }}\n  return `{}`\n}}\n", key.sanitized));
+      body
+    }
+    Lookup::Record => {
+      let mut body = String::new();
+      let mut entries = String::new();
+      let mut shared = 0;
+
+      for (locales, template) in &groups {
+        let value = if share_template(locales.len(), template) {
+          let name = format!("shared{shared}");
+          body.push_str(&format!("  const {name} = `{template}`\n"));
+          shared += 1;
+          name
+        } else {
+          format!("`{template}`")
+        };
+
+        for locale in locales {
+          entries.push_str(&format!("    \"{locale}\": {value},\n"));
+        }
+      }
+
+      body.push_str(&format!(
+        "  const translations: Record<string, string | undefined> = {{\n{entries}  }}\n"
+      ));
+      body.push_str(&format!(
+        "  return translations[locale ?? getLocale()] ?? `{}`\n}}\n",
+        key.sanitized
+      ));
+      body
+    }
+  }
+}
+
+/// Estimates the size of all message functions after minification, with if-chains and with
+/// lookup tables
+pub fn estimate_sizes(module: &Module) -> SizeEstimate {
+  let mut estimate = SizeEstimate {
+    if_chains: 0,
+    lookup_tables: 0,
+  };
+
+  for (key, message) in &module.messages {
+    let groups = template_groups(message);
+    estimate.if_chains += minified(Lookup::IfChain, key, &groups).len();
+    estimate.lookup_tables += minified(smallest(key, message, &groups), key, &groups).len();
+  }
+
+  for module in module.modules.values() {
+    let nested = estimate_sizes(module);
+    estimate.if_chains += nested.if_chains;
+    estimate.lookup_tables += nested.lookup_tables;
+  }
+
+  estimate
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn lookup_tables() {
+    let module = module(&[
+      ("en", TRANSLATIONS),
+      ("en-GB", TRANSLATIONS),
+      (
+        "de",
+        r#"
+title = "Nachrichten"
+greeting = "Hallo, {name}!"
+brand = "Woof"
+"#,
+      ),
+    ]);
+
+    let bodies = module
+      .messages
+      .iter()
+      .map(|(key, message)| format!("{}:\n{}", key.sanitized, lookup_function_body(key, message)))
+      .collect::<Vec<_>>();
+
+    insta::assert_snapshot!(format!(
+      "{}---\n{:?}",
+      bodies.join("\n"),
+      estimate_sizes(&module)
+    ));
+  }
+
+  const TRANSLATIONS: &str = r#"
+title = "Messages"
+greeting = "Hello, {name}!"
+brand = "Woof"
+only_english = "Only in English"
+"#;
+}
@@ -0,0 +1,534 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{PluralRule, SelectCondition, VERSION, select_conditions};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{DEFAULT_LOCALE, Key, Locale, Message, Module, Part, Variant};
+use crate::sanitize::{escape_keyword, unescape_translation};
+
+static KEYWORDS: &[&str] = &[
+  "False",
+  "None",
+  "True",
+  "and",
+  "as",
+  "assert",
+  "async",
+  "await",
+  "break",
+  "case",
+  "class",
+  "continue",
+  "def",
+  "del",
+  "elif",
+  "else",
+  "except",
+  "finally",
+  "for",
+  "from",
+  "global",
+  "if",
+  "import",
+  "in",
+  "is",
+  "lambda",
+  "match",
+  "nonlocal",
+  "not",
+  "or",
+  "pass",
+  "raise",
+  "return",
+  "try",
+  "type",
+  "while",
+  "with",
+  "yield",
+  // Used for the parameters and variables of every message function
+  "locale",
+  "resolved",
+  // Imported into the modules from the locale module or `typing`
+  "Locale",
+  "Literal",
+  "current_locale",
+  "default_locale",
+  "get_locale",
+  "plural_category",
+  "set_locale_fn",
+];
+
+static LOCALE_MODULE: &str = "_locale";
+
+/// Writes a Python package mirroring the messages, with an `__init__.py` file per module.
+///
+/// Every message becomes a function taking its interpolations as keyword arguments. The locale
+/// is read from a context variable, unless a different getter is set with `set_locale_fn`.
+pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  if dir.exists() {
+    fs::remove_dir_all(dir)?;
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  fs::write(
+    dir.join(format!("{LOCALE_MODULE}.py")),
+    write_locale_module(&locales),
+  )?;
+
+  write_package(dir, 0, module)
+}
+
+fn write_package(dir: &Path, depth: usize, module: &Module) -> Result<(), WoofError> {
+  fs::write(dir.join("__init__.py"), write_module(depth, module))?;
+
+  for (key, module) in &module.modules {
+    let dir = dir.join(ident(key));
+    fs::create_dir_all(&dir)?;
+    write_package(&dir, depth + 1, module)?;
+  }
+
+  Ok(())
+}
+
+fn header() -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "# Code generated by woof v{VERSION}. DO NOT EDIT.");
+  let _ = writeln!(out, "# ruff: noqa");
+  let _ = writeln!(out, "# flake8: noqa");
+  let _ = writeln!(out, "from __future__ import annotations");
+  out
+}
+
+fn write_locale_module(locales: &[Locale]) -> String {
+  let mut out = header();
+  let union = locales
+    .iter()
+    .map(|locale| string(&locale.0))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  let _ = writeln!(out, "from contextvars import ContextVar");
+  let _ = writeln!(out, "from typing import Callable, Literal");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "Locale = Literal[{union}]");
+  let _ = writeln!(out, "default_locale: Locale = {}", string(DEFAULT_LOCALE));
+  let _ = writeln!(
+    out,
+    "current_locale: ContextVar[Locale] = ContextVar(\"current_locale\", default=default_locale)"
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(out, "_locale_fn: Callable[[], Locale] = current_locale.get");
+  let _ = writeln!(out);
+  let _ = writeln!(out);
+  let _ = writeln!(out, "def get_locale() -> Locale:");
+  let _ = writeln!(out, "    return _locale_fn()");
+  let _ = writeln!(out);
+  let _ = writeln!(out);
+  let _ = writeln!(out, "def set_locale_fn(fn: Callable[[], Locale]) -> None:");
+  let _ = writeln!(out, "    global _locale_fn");
+  let _ = writeln!(out, "    _locale_fn = fn");
+  let _ = writeln!(out);
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "def plural_category(locale: Locale, n: int | float) -> str:"
+  );
+
+  let rules = PluralRule::group(locales);
+
+  // Locales with the English rules use the fallback at the end
+  for (rule, locales) in rules {
+    let locales = locales
+      .iter()
+      .map(|locale| string(&locale.0))
+      .collect::<Vec<_>>()
+      .join(", ");
+
+    let _ = writeln!(out, "    if locale in [{locales}]:");
+    for line in plural_rule(rule) {
+      let _ = writeln!(out, "        {line}");
+    }
+  }
+
+  let _ = writeln!(out, "    return \"one\" if n == 1 else \"other\"");
+  out
+}
+
+fn write_module(depth: usize, module: &Module) -> String {
+  let mut out = header();
+  let root = ".".repeat(depth + 1);
+
+  let uses_enums = module.messages.values().any(|message| {
+    message
+      .interpolations
+      .values()
+      .any(|i| matches!(i.type_, InterpolationType::Enum(_)))
+  });
+
+  if uses_enums {
+    let _ = writeln!(out, "from typing import Literal");
+  }
+
+  if depth == 0 {
+    let _ = writeln!(
+      out,
+      "from .{LOCALE_MODULE} import Locale, current_locale, default_locale, get_locale, plural_category, set_locale_fn"
+    );
+  } else if !module.messages.is_empty() {
+    let _ = writeln!(
+      out,
+      "from {root}{LOCALE_MODULE} import Locale, get_locale, plural_category"
+    );
+  }
+
+  for key in module.modules.keys() {
+    let _ = writeln!(out, "from . import {}", ident(key));
+  }
+
+  for (key, message) in &module.messages {
+    let _ = writeln!(out);
+    let _ = writeln!(out);
+    let _ = write!(out, "def {}(", ident(key));
+
+    if !message.interpolations.is_empty() {
+      let _ = write!(out, "*, ");
+      // Arguments that every translation has a default for can be omitted
+      for (key, interpolation) in &message.interpolations {
+        let type_ = python_type(&interpolation.type_);
+        if interpolation.required {
+          let _ = write!(out, "{}: {type_}, ", ident(key));
+        } else {
+          let _ = write!(out, "{}: {type_} | None = None, ", ident(key));
+        }
+      }
+    }
+
+    let _ = writeln!(out, "locale: Locale | None = None) -> str:");
+    let _ = writeln!(out, "    resolved = locale or get_locale()");
+
+    for locale in message.translation.keys() {
+      if let Some(parts) = message.parts_for_locale(locale) {
+        let _ = writeln!(out, "    if resolved == {}:", string(&locale.0));
+        let _ = writeln!(out, "        return {}", expression(message, &parts));
+      }
+    }
+
+    let _ = writeln!(out, "    return {}", string(&key.sanitized));
+  }
+
+  out
+}
+
+/// Renders parts as an expression evaluating to a `str`. Plain interpolations use f-strings,
+/// selects and optional arguments are concatenated as conditional expressions.
+fn expression(message: &Message, parts: &[Part]) -> String {
+  if let [Part::Select { name, variants }] = parts {
+    return select(message, name, variants);
+  }
+
+  let conditional = |part: &Part| match part {
+    Part::Select { .. } => true,
+    Part::Interpolation { name, default } => {
+      default.is_some() && !message.interpolations[name].required
+    }
+    Part::Text(_) => false,
+  };
+
+  if parts.iter().any(conditional) {
+    return parts
+      .iter()
+      .map(|part| match part {
+        Part::Select { name, variants } => format!("({})", select(message, name, variants)),
+        Part::Interpolation {
+          name,
+          default: Some(default),
+        } if conditional(part) => {
+          let arg = ident(name);
+          let value = match message.interpolations[name].type_ {
+            InterpolationType::Boolean => format!("str({arg}).lower()"),
+            InterpolationType::Number => format!("str({arg})"),
+            _ => arg.clone(),
+          };
+
+          format!(
+            "({value} if {arg} is not None else {})",
+            string(&unescape_translation(default))
+          )
+        }
+        part => expression(message, std::slice::from_ref(part)),
+      })
+      .collect::<Vec<_>>()
+      .join(" + ");
+  }
+
+  let mut text = String::new();
+  let mut format = String::new();
+
+  for part in parts {
+    match part {
+      Part::Text(t) => {
+        let t = unescape_translation(t);
+        format.push_str(&t.replace('{', "{{").replace('}', "}}"));
+        text.push_str(&t);
+      }
+      Part::Interpolation { name: key, .. } => {
+        // Booleans are formatted like in the other languages instead of as `True` and `False`
+        if message.interpolations[key].type_ == InterpolationType::Boolean {
+          let _ = write!(format, "{{str({}).lower()}}", ident(key));
+        } else {
+          let _ = write!(format, "{{{}}}", ident(key));
+        }
+      }
+      Part::Select { .. } => unreachable!("selects are handled above"),
+    }
+  }
+
+  if parts.iter().all(|part| matches!(part, Part::Text(_))) {
+    string(&text)
+  } else {
+    format!("f{}", string(&format))
+  }
+}
+
+/// Renders a select as a chain of conditional expressions of its [`select_conditions`]
+fn select(message: &Message, name: &Key, variants: &[Variant]) -> String {
+  let arg = ident(name);
+  let conditions = select_conditions(message, name, variants)
+    .into_iter()
+    .map(|(condition, variant)| {
+      let condition = match condition {
+        SelectCondition::Boolean(true) => arg.clone(),
+        SelectCondition::Boolean(false) => format!("not {arg}"),
+        SelectCondition::Equals(key) => format!("{arg} == {}", string(key)),
+        SelectCondition::Number(number) => format!("{arg} == {number}"),
+        SelectCondition::PluralCategory(key) => {
+          format!("plural_category(resolved, {arg}) == {}", string(key))
+        }
+      };
+
+      (condition, variant)
+    })
+    .collect::<Vec<_>>();
+
+  let default = variants
+    .iter()
+    .find(|v| v.default)
+    .map(|v| expression(message, &v.parts))
+    .unwrap_or_else(|| "\"\"".to_string());
+
+  let mut out = String::new();
+  for (condition, variant) in conditions {
+    let _ = write!(
+      out,
+      "{} if {condition} else ",
+      expression(message, &variant.parts)
+    );
+  }
+
+  out.push_str(&default);
+  out
+}
+
+/// The body of `plural_category` for a plural rule
+fn plural_rule(rule: PluralRule) -> &'static [&'static str] {
+  match rule {
+    PluralRule::Other => &["return \"other\""],
+    PluralRule::One => &["return \"one\" if n == 1 else \"other\""],
+    PluralRule::ZeroOne => &["return \"one\" if abs(n) < 2 else \"other\""],
+    PluralRule::EastSlavic => &[
+      "if n % 1:",
+      "    return \"other\"",
+      "i = int(abs(n))",
+      "if i % 10 == 1 and i % 100 != 11:",
+      "    return \"one\"",
+      "if 2 <= i % 10 <= 4 and not 12 <= i % 100 <= 14:",
+      "    return \"few\"",
+      "return \"many\"",
+    ],
+    PluralRule::Polish => &[
+      "if n % 1:",
+      "    return \"other\"",
+      "i = int(abs(n))",
+      "if i == 1:",
+      "    return \"one\"",
+      "if 2 <= i % 10 <= 4 and not 12 <= i % 100 <= 14:",
+      "    return \"few\"",
+      "return \"many\"",
+    ],
+    PluralRule::Czech => &[
+      "if n % 1:",
+      "    return \"many\"",
+      "if n == 1:",
+      "    return \"one\"",
+      "if 2 <= n <= 4:",
+      "    return \"few\"",
+      "return \"other\"",
+    ],
+    PluralRule::Arabic => &[
+      "if n % 1:",
+      "    return \"other\"",
+      "i = int(abs(n))",
+      "if i in (0, 1, 2):",
+      "    return (\"zero\", \"one\", \"two\")[i]",
+      "if 3 <= i % 100 <= 10:",
+      "    return \"few\"",
+      "if 11 <= i % 100 <= 99:",
+      "    return \"many\"",
+      "return \"other\"",
+    ],
+  }
+}
+
+fn python_type(type_: &InterpolationType) -> String {
+  match type_ {
+    InterpolationType::None | InterpolationType::String | InterpolationType::Custom(_) => {
+      "str".to_string()
+    }
+    InterpolationType::Number => "int | float".to_string(),
+    InterpolationType::Boolean => "bool".to_string(),
+    InterpolationType::Enum(cases) => format!(
+      "Literal[{}]",
+      cases
+        .iter()
+        .map(|case| string(case))
+        .collect::<Vec<_>>()
+        .join(", ")
+    ),
+  }
+}
+
+fn ident(key: &Key) -> String {
+  escape_keyword(&key.sanitized, KEYWORDS)
+}
+
+/// Quotes a string as a Python string literal
+fn string(s: &str) -> String {
+  let mut result = String::with_capacity(s.len() + 2);
+  result.push('"');
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(result, "\\u{:04x}", c as u32);
+      }
+      c => result.push(c),
+    }
+  }
+
+  result.push('"');
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn python_package() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! {{Braces} and \"quotes\""
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *other {They}} replied to {lambda}"
+only_english = "Only in English"
+
+[nested.module]
+storage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "ru",
+        r#"
+greeting = "Привет, {name}!"
+items = "{count:number -> one {{count} элемент} few {{count} элемента} *other {{count} элементов}}"
+reply = "{gender -> female {Она} *other {Они}} ответили {lambda}"
+
+[nested.module]
+storage = "{used:number} из {total:number}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("ru".to_string())];
+    let nested = &module.modules[&Key::new("nested")].modules[&Key::new("module")];
+
+    insta::assert_snapshot!(format!(
+      "{}\n---\n{}\n---\n{}",
+      write_locale_module(&locales),
+      write_module(0, &module),
+      write_module(2, nested)
+    ));
+  }
+
+  #[test]
+  fn runtime_names_are_escaped() {
+    let module = module(&[(
+      "en",
+      r#"
+get_locale = "Get"
+set_locale_fn = "Set"
+plural_category = "Category"
+Locale = "Locale"
+Literal = "{kind:enum(a|b) -> a {A} *b {B}}"
+"#,
+    )]);
+
+    let out = write_module(0, &module);
+    for name in [
+      "get_locale",
+      "set_locale_fn",
+      "plural_category",
+      "Locale",
+      "Literal",
+    ] {
+      assert!(
+        out.contains(&format!("def {name}_(")),
+        "{name} isn't escaped"
+      );
+    }
+  }
+
+  #[test]
+  fn optional_args() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+items = "{count:number=No} items"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo {name=du}{suffix?}"
+items = "{count:number=Keine} Einträge"
+signature = "Viele Grüße, {sender}"
+"#,
+      ),
+    ]);
+
+    insta::assert_snapshot!(write_module(0, &module));
+  }
+}
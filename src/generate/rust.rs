@@ -0,0 +1,447 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::iter::repeat_n;
+use std::path::Path;
+
+use super::{PluralRule, SelectCondition, VERSION, locale_ident, select_conditions};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{DEFAULT_LOCALE, Key, Locale, Message, Module, Part, Variant};
+use crate::sanitize::{escape_keyword, unescape_translation};
+
+static KEYWORDS: &[&str] = &[
+  "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+  "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+  "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "try",
+  "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+  "override", "priv", "typeof", "unsized", "virtual", "yield",
+  // Used for the locale parameter of every message function
+  "locale",
+];
+
+/// Writes a Rust module tree mirroring the messages, with a `mod.rs` file per module. The root
+/// module contains a `Locale` enum with all collected locales. It has no inner attributes, so it
+/// can also be used with `include!` from a build script's `OUT_DIR`.
+///
+/// Every message becomes a function taking the locale and its interpolations as arguments.
+pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  if dir.exists() {
+    fs::remove_dir_all(dir)?;
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  let mut out = header(0);
+  write_locale_enum(&mut out, &locales);
+  fs::write(dir.join("mod.rs"), write_module(out, 0, module, &locales))?;
+
+  write_submodules(dir, 0, module, &locales)
+}
+
+fn write_submodules(
+  dir: &Path,
+  depth: usize,
+  module: &Module,
+  locales: &[Locale],
+) -> Result<(), WoofError> {
+  for (key, module) in &module.modules {
+    let dir = dir.join(ident(key));
+    fs::create_dir_all(&dir)?;
+    fs::write(
+      dir.join("mod.rs"),
+      write_module(header(depth + 1), depth + 1, module, locales),
+    )?;
+
+    write_submodules(&dir, depth + 1, module, locales)?;
+  }
+
+  Ok(())
+}
+
+/// Lints that generated code doesn't follow, allowed for the items of the root module and in
+/// every other module
+static ALLOW: &str = "allow(clippy::all, unused)";
+
+fn header(depth: usize) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "// Code generated by woof v{VERSION}. DO NOT EDIT.");
+  if depth > 0 {
+    let _ = writeln!(out, "#![{ALLOW}]");
+  }
+  out
+}
+
+fn write_locale_enum(out: &mut String, locales: &[Locale]) {
+  let has_default = locales.iter().any(|l| l.0 == DEFAULT_LOCALE);
+
+  let _ = writeln!(out);
+  let _ = writeln!(out, "#[{ALLOW}]");
+  if has_default {
+    let _ = writeln!(
+      out,
+      "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]"
+    );
+  } else {
+    let _ = writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]");
+  }
+  let _ = writeln!(out, "pub enum Locale {{");
+  for locale in locales {
+    if locale.0 == DEFAULT_LOCALE {
+      let _ = writeln!(out, "    #[default]");
+    }
+    let _ = writeln!(out, "    {},", locale_ident(locale));
+  }
+  let _ = writeln!(out, "}}");
+
+  let _ = writeln!(out);
+  let _ = writeln!(out, "#[{ALLOW}]");
+  let _ = writeln!(out, "impl Locale {{");
+  let _ = writeln!(out, "    pub const ALL: &'static [Locale] = &[");
+  for locale in locales {
+    let _ = writeln!(out, "        Locale::{},", locale_ident(locale));
+  }
+  let _ = writeln!(out, "    ];");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "    pub fn as_str(self) -> &'static str {{");
+  let _ = writeln!(out, "        match self {{");
+  for locale in locales {
+    let _ = writeln!(
+      out,
+      "            Locale::{} => {:?},",
+      locale_ident(locale),
+      locale.0
+    );
+  }
+  let _ = writeln!(out, "        }}");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "    /// Returns the plural category of a number, e.g. `\"one\"` or `\"other\"`"
+  );
+  let _ = writeln!(
+    out,
+    "    pub fn plural_category(self, n: f64) -> &'static str {{"
+  );
+  let _ = writeln!(out, "        match self {{");
+  for locale in locales {
+    let _ = writeln!(
+      out,
+      "            Locale::{} => {},",
+      locale_ident(locale),
+      plural_rule(PluralRule::for_locale(locale))
+    );
+  }
+  let _ = writeln!(out, "        }}");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out, "}}");
+
+  let _ = writeln!(out);
+  let _ = writeln!(out, "#[{ALLOW}]");
+  let _ = writeln!(out, "impl std::fmt::Display for Locale {{");
+  let _ = writeln!(
+    out,
+    "    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+  );
+  let _ = writeln!(out, "        f.write_str(self.as_str())");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out, "}}");
+
+  let _ = writeln!(out);
+  let _ = writeln!(out, "#[{ALLOW}]");
+  let _ = writeln!(out, "impl std::str::FromStr for Locale {{");
+  let _ = writeln!(out, "    type Err = ();");
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "    fn from_str(s: &str) -> Result<Self, Self::Err> {{"
+  );
+  let _ = writeln!(out, "        match s {{");
+  for locale in locales {
+    let _ = writeln!(
+      out,
+      "            {:?} => Ok(Locale::{}),",
+      locale.0,
+      locale_ident(locale)
+    );
+  }
+  let _ = writeln!(out, "            _ => Err(()),");
+  let _ = writeln!(out, "        }}");
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out, "}}");
+}
+
+fn write_module(mut out: String, depth: usize, module: &Module, locales: &[Locale]) -> String {
+  if depth > 0 && !module.messages.is_empty() {
+    let root = repeat_n("super", depth).collect::<Vec<_>>().join("::");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "use {root}::Locale;");
+  }
+
+  if !module.modules.is_empty() {
+    let _ = writeln!(out);
+  }
+
+  for key in module.modules.keys() {
+    if depth == 0 {
+      let _ = writeln!(out, "#[{ALLOW}]");
+    }
+    let _ = writeln!(out, "pub mod {};", ident(key));
+  }
+
+  for (key, message) in &module.messages {
+    let _ = writeln!(out);
+    if depth == 0 {
+      let _ = writeln!(out, "#[{ALLOW}]");
+    }
+    let _ = write!(out, "pub fn {}(locale: Locale", ident(key));
+
+    // Arguments that every translation has a default for can be omitted
+    for (key, interpolation) in &message.interpolations {
+      let type_ = rust_type(&interpolation.type_);
+      if interpolation.required {
+        let _ = write!(out, ", {}: {type_}", ident(key));
+      } else {
+        let _ = write!(out, ", {}: Option<{type_}>", ident(key));
+      }
+    }
+
+    let _ = writeln!(out, ") -> String {{");
+    let _ = writeln!(out, "    match locale {{");
+
+    for locale in message.translation.keys() {
+      if let Some(parts) = message.parts_for_locale(locale) {
+        let _ = writeln!(
+          out,
+          "        Locale::{} => {},",
+          locale_ident(locale),
+          expression(message, &parts)
+        );
+      }
+    }
+
+    if locales
+      .iter()
+      .any(|locale| !message.translation.contains_key(locale))
+    {
+      let _ = writeln!(out, "        _ => String::from({:?}),", key.sanitized);
+    }
+
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+  }
+
+  out
+}
+
+/// Renders parts as an expression evaluating to a `String`
+fn expression(message: &Message, parts: &[Part]) -> String {
+  if let [Part::Select { name, variants }] = parts {
+    return select(message, name, variants);
+  }
+
+  let mut text = String::new();
+  let mut format = String::new();
+  let mut args = Vec::new();
+
+  for part in parts {
+    match part {
+      Part::Text(t) => {
+        let t = unescape_translation(t);
+        format.push_str(&t.replace('{', "{{").replace('}', "}}"));
+        text.push_str(&t);
+      }
+      Part::Interpolation { name: key, default } => {
+        format.push_str("{}");
+        args.push(interpolation(message, key, default.as_deref()));
+      }
+      Part::Select { name, variants } => {
+        format.push_str("{}");
+        args.push(select(message, name, variants));
+      }
+    }
+  }
+
+  if args.is_empty() {
+    return format!("String::from({text:?})");
+  }
+
+  format!("format!({format:?}, {})", args.join(", "))
+}
+
+/// Renders an interpolation, falling back to its default if the argument is optional
+fn interpolation(message: &Message, key: &Key, default: Option<&str>) -> String {
+  let arg = ident(key);
+  let interpolation = &message.interpolations[key];
+  let Some(default) = default.filter(|_| !interpolation.required) else {
+    return arg;
+  };
+
+  let default = unescape_translation(default);
+  match interpolation.type_ {
+    InterpolationType::Number | InterpolationType::Boolean => {
+      format!("{arg}.map_or_else(|| String::from({default:?}), |{arg}| {arg}.to_string())")
+    }
+    _ => format!("{arg}.unwrap_or({default:?})"),
+  }
+}
+
+/// Renders a select as an if-chain of its [`select_conditions`]
+fn select(message: &Message, name: &Key, variants: &[Variant]) -> String {
+  let arg = ident(name);
+  let conditions = select_conditions(message, name, variants)
+    .into_iter()
+    .map(|(condition, variant)| {
+      let condition = match condition {
+        SelectCondition::Boolean(true) => arg.clone(),
+        SelectCondition::Boolean(false) => format!("!{arg}"),
+        SelectCondition::Equals(key) => format!("{arg} == {key:?}"),
+        SelectCondition::Number(number) => format!("{arg} == {number:?}"),
+        SelectCondition::PluralCategory(key) => format!("locale.plural_category({arg}) == {key:?}"),
+      };
+
+      (condition, variant)
+    })
+    .collect::<Vec<_>>();
+
+  let default = variants
+    .iter()
+    .find(|v| v.default)
+    .map(|v| expression(message, &v.parts))
+    .unwrap_or_else(|| "String::new()".to_string());
+
+  if conditions.is_empty() {
+    return default;
+  }
+
+  let mut out = String::new();
+  for (condition, variant) in conditions {
+    let _ = write!(
+      out,
+      "if {condition} {{ {} }} else ",
+      expression(message, &variant.parts)
+    );
+  }
+
+  let _ = write!(out, "{{ {default} }}");
+  out
+}
+
+fn rust_type(type_: &InterpolationType) -> &'static str {
+  match type_ {
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => "&str",
+    InterpolationType::Number => "f64",
+    InterpolationType::Boolean => "bool",
+  }
+}
+
+fn ident(key: &Key) -> String {
+  escape_keyword(&key.sanitized, KEYWORDS)
+}
+
+/// The body of `Locale::plural_category` for a plural rule
+fn plural_rule(rule: PluralRule) -> &'static str {
+  match rule {
+    PluralRule::Other => r#""other""#,
+    PluralRule::One => r#"if n == 1.0 { "one" } else { "other" }"#,
+    PluralRule::ZeroOne => r#"if n.abs() < 2.0 { "one" } else { "other" }"#,
+    PluralRule::EastSlavic => {
+      r#"if n.fract() != 0.0 { "other" } else { let i = n.abs() as i64; if i % 10 == 1 && i % 100 != 11 { "one" } else if (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100)) { "few" } else { "many" } }"#
+    }
+    PluralRule::Polish => {
+      r#"if n.fract() != 0.0 { "other" } else { let i = n.abs() as i64; if i == 1 { "one" } else if (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100)) { "few" } else { "many" } }"#
+    }
+    PluralRule::Czech => {
+      r#"if n.fract() != 0.0 { "many" } else if n == 1.0 { "one" } else if (2.0..=4.0).contains(&n) { "few" } else { "other" }"#
+    }
+    PluralRule::Arabic => {
+      r#"if n.fract() != 0.0 { "other" } else { let i = n.abs() as i64; match i { 0 => "zero", 1 => "one", 2 => "two", _ if (3..=10).contains(&(i % 100)) => "few", _ if (11..=99).contains(&(i % 100)) => "many", _ => "other" } }"#
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn rust_module() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! {{Braces} and \"quotes\""
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *other {They}} replied to {type}"
+type = "Keyword"
+only_english = "Only in English"
+
+[nested.module]
+storage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "pt-BR",
+        r#"
+greeting = "Olá, {name}!"
+items = "{count:number -> one {Um item} *other {{count} itens}}"
+reply = "{gender -> female {Ela} *other {Eles}} responderam {type}"
+type = "Palavra"
+
+[nested.module]
+storage = "{used:number} de {total:number}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("pt-BR".to_string())];
+    let mut root = header(0);
+    write_locale_enum(&mut root, &locales);
+
+    let nested = &module.modules[&Key::new("nested")].modules[&Key::new("module")];
+
+    insta::assert_snapshot!(format!(
+      "{}\n---\n{}",
+      write_module(root, 0, &module, &locales),
+      write_module(header(2), 2, nested, &locales)
+    ));
+  }
+
+  #[test]
+  fn optional_args() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+items = "{count:number=No} items"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo {name=du}{suffix?}"
+items = "{count:number=Keine} Einträge"
+signature = "Viele Grüße, {sender}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("de".to_string()), Locale("en".to_string())];
+    insta::assert_snapshot!(write_module(String::new(), 0, &module, &locales));
+  }
+}
@@ -0,0 +1,491 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::{
+  Namespaces, PluralRule, SelectCondition, VERSION, initial_locale, locale_ident,
+  select_conditions, write_namespaces,
+};
+use crate::errors::WoofError;
+use crate::interpolations::InterpolationType;
+use crate::parse::{Key, Locale, Message, Module, Part, Variant};
+use crate::sanitize::{escape_keyword, unescape_translation};
+
+static KEYWORDS: &[&str] = &[
+  "associatedtype",
+  "class",
+  "deinit",
+  "enum",
+  "extension",
+  "fileprivate",
+  "func",
+  "import",
+  "init",
+  "inout",
+  "internal",
+  "let",
+  "open",
+  "operator",
+  "private",
+  "precedencegroup",
+  "protocol",
+  "public",
+  "rethrows",
+  "static",
+  "struct",
+  "subscript",
+  "typealias",
+  "var",
+  "break",
+  "case",
+  "catch",
+  "continue",
+  "default",
+  "defer",
+  "do",
+  "else",
+  "fallthrough",
+  "for",
+  "guard",
+  "if",
+  "in",
+  "repeat",
+  "return",
+  "throw",
+  "switch",
+  "where",
+  "while",
+  "Any",
+  "as",
+  "await",
+  "false",
+  "is",
+  "nil",
+  "self",
+  "Self",
+  "super",
+  "throws",
+  "true",
+  "try",
+  "Type",
+  "Protocol",
+  // Declared in the root enum, or used by every message function
+  "m",
+  "Locale",
+  "defaultLocale",
+  "localeFn",
+  "getLocale",
+  "setLocaleFn",
+  "formatNumber",
+  "pluralCategory",
+  "locale",
+  "resolved",
+];
+
+static FILENAME: &str = "Messages.swift";
+
+/// Writes a `Messages.swift` file with an enum `m` mirroring the messages, with a nested enum
+/// per module.
+///
+/// Every message becomes a static function taking its interpolations and an optional locale,
+/// which defaults to the locale returned by the function set with `m.setLocaleFn`. Other files
+/// in the output directory are kept.
+pub fn generate(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let mut locales = locales.to_vec();
+  locales.sort();
+
+  fs::write(dir.join(FILENAME), write_file(&locales, module))?;
+  Ok(())
+}
+
+fn write_file(locales: &[Locale], module: &Module) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "// Code generated by woof v{VERSION}. DO NOT EDIT.");
+  let _ = writeln!(out);
+  let _ = writeln!(out, "enum m {{");
+  let _ = writeln!(out, "    enum Locale: String, CaseIterable {{");
+  for locale in locales {
+    let _ = writeln!(
+      out,
+      "        case {} = {}",
+      case_name(locale),
+      string(&locale.0)
+    );
+  }
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+
+  let default = initial_locale(locales)
+    .map(|locale| format!(".{}", case_name(locale)))
+    .unwrap_or_else(|| "Locale.allCases[0]".to_string());
+
+  let _ = writeln!(out, "    static let defaultLocale: Locale = {default}");
+  let _ = writeln!(
+    out,
+    "    nonisolated(unsafe) private static var localeFn: () -> Locale = {{ m.defaultLocale }}"
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "    static func getLocale() -> Locale {{ localeFn() }}"
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "    static func setLocaleFn(_ fn: @escaping () -> Locale) {{ localeFn = fn }}"
+  );
+  let _ = writeln!(out);
+  let _ = writeln!(
+    out,
+    "    static func formatNumber(_ n: Double) -> String {{"
+  );
+  let _ = writeln!(
+    out,
+    "        n.truncatingRemainder(dividingBy: 1) == 0 && Swift.abs(n) < 1e15 ? String(Int64(n)) : String(n)"
+  );
+  let _ = writeln!(out, "    }}");
+  let _ = writeln!(out);
+  write_plural_category(&mut out, locales);
+
+  write_namespaces(&mut out, 1, module, &Swift { locales });
+  let _ = writeln!(out, "}}");
+  out
+}
+
+fn write_plural_category(out: &mut String, locales: &[Locale]) {
+  let rules = PluralRule::group(locales);
+
+  let _ = writeln!(
+    out,
+    "    static func pluralCategory(_ locale: Locale, _ n: Double) -> String {{"
+  );
+
+  // Locales with the English rules use the fallback at the end
+  for (rule, locales) in rules {
+    let locales = locales
+      .iter()
+      .map(|locale| format!(".{}", case_name(locale)))
+      .collect::<Vec<_>>()
+      .join(", ");
+
+    let _ = writeln!(out, "        if [{locales}].contains(locale) {{");
+    for line in plural_rule(rule) {
+      let _ = writeln!(out, "            {line}");
+    }
+    let _ = writeln!(out, "        }}");
+  }
+
+  for line in plural_rule(PluralRule::One) {
+    let _ = writeln!(out, "        {line}");
+  }
+  let _ = writeln!(out, "    }}");
+}
+
+struct Swift<'a> {
+  locales: &'a [Locale],
+}
+
+impl Namespaces for Swift<'_> {
+  fn write_message(&self, out: &mut String, key: &Key, message: &Message, indent: &str) {
+    let _ = write!(out, "{indent}static func {}(", ident(key));
+
+    // Arguments that every translation has a default for can be omitted
+    for (key, interpolation) in &message.interpolations {
+      let type_ = swift_type(&interpolation.type_);
+      if interpolation.required {
+        let _ = write!(out, "{}: {type_}, ", ident(key));
+      } else {
+        let _ = write!(out, "{}: {type_}? = nil, ", ident(key));
+      }
+    }
+
+    let _ = writeln!(out, "locale: m.Locale? = nil) -> String {{");
+    let _ = writeln!(out, "{indent}    let resolved = locale ?? m.getLocale()");
+    let _ = writeln!(out, "{indent}    switch resolved {{");
+
+    for locale in message.translation.keys() {
+      if let Some(parts) = message.parts_for_locale(locale) {
+        let _ = writeln!(
+          out,
+          "{indent}    case .{}: return {}",
+          case_name(locale),
+          expression(message, &parts)
+        );
+      }
+    }
+
+    // Switches over all cases can't have a default
+    if self
+      .locales
+      .iter()
+      .any(|locale| !message.translation.contains_key(locale))
+    {
+      let _ = writeln!(
+        out,
+        "{indent}    default: return {}",
+        string(&key.sanitized)
+      );
+    }
+
+    let _ = writeln!(out, "{indent}    }}");
+    let _ = writeln!(out, "{indent}}}");
+  }
+
+  fn open_namespace(&self, out: &mut String, key: &Key, indent: &str) {
+    let _ = writeln!(out, "{indent}enum {} {{", ident(key));
+  }
+
+  fn close_namespace(&self, out: &mut String, indent: &str) {
+    let _ = writeln!(out, "{indent}}}");
+  }
+}
+
+/// Renders parts as an interpolated string literal, with selects as conditional expressions
+fn expression(message: &Message, parts: &[Part]) -> String {
+  if let [Part::Select { name, variants }] = parts {
+    return select(message, name, variants);
+  }
+
+  let mut out = String::from("\"");
+
+  for part in parts {
+    match part {
+      Part::Text(text) => out.push_str(&escape(&unescape_translation(text))),
+      Part::Interpolation {
+        name: key,
+        default: Some(default),
+      } if !message.interpolations[key].required => {
+        let arg = ident(key);
+        let value = match message.interpolations[key].type_ {
+          InterpolationType::Number => format!("{arg}.map {{ m.formatNumber($0) }}"),
+          InterpolationType::Boolean => format!("{arg}.map {{ String($0) }}"),
+          _ => arg,
+        };
+
+        let default = string(&unescape_translation(default));
+        let _ = write!(out, "\\({value} ?? {default})");
+      }
+      Part::Interpolation { name: key, .. } => match message.interpolations[key].type_ {
+        InterpolationType::Number => {
+          let _ = write!(out, "\\(m.formatNumber({}))", ident(key));
+        }
+        InterpolationType::None
+        | InterpolationType::String
+        | InterpolationType::Boolean
+        | InterpolationType::Enum(_)
+        | InterpolationType::Custom(_) => {
+          let _ = write!(out, "\\({})", ident(key));
+        }
+      },
+      Part::Select { name, variants } => {
+        let _ = write!(out, "\\({})", select(message, name, variants));
+      }
+    }
+  }
+
+  out.push('"');
+  out
+}
+
+/// Renders a select as a chain of conditional expressions of its [`select_conditions`]
+fn select(message: &Message, name: &Key, variants: &[Variant]) -> String {
+  let arg = ident(name);
+  let conditions = select_conditions(message, name, variants)
+    .into_iter()
+    .map(|(condition, variant)| {
+      let condition = match condition {
+        SelectCondition::Boolean(true) => arg.clone(),
+        SelectCondition::Boolean(false) => format!("!{arg}"),
+        SelectCondition::Equals(key) => format!("{arg} == {}", string(key)),
+        SelectCondition::Number(number) => format!("{arg} == {number:?}"),
+        SelectCondition::PluralCategory(key) => {
+          format!("m.pluralCategory(resolved, {arg}) == {}", string(key))
+        }
+      };
+
+      (condition, variant)
+    })
+    .collect::<Vec<_>>();
+
+  let default = variants
+    .iter()
+    .find(|v| v.default)
+    .map(|v| expression(message, &v.parts))
+    .unwrap_or_else(|| "\"\"".to_string());
+
+  let mut out = String::new();
+  for (condition, variant) in conditions {
+    let _ = write!(
+      out,
+      "{condition} ? {} : ",
+      expression(message, &variant.parts)
+    );
+  }
+
+  out.push_str(&default);
+  out
+}
+
+/// The body of `pluralCategory` for a plural rule. Only the last two digits are converted to
+/// integers, since converting large numbers would trap.
+fn plural_rule(rule: PluralRule) -> &'static [&'static str] {
+  match rule {
+    PluralRule::Other => &["return \"other\""],
+    PluralRule::One => &["return n == 1 ? \"one\" : \"other\""],
+    PluralRule::ZeroOne => &["return Swift.abs(n) < 2 ? \"one\" : \"other\""],
+    PluralRule::EastSlavic => &[
+      "if n.truncatingRemainder(dividingBy: 1) != 0 { return \"other\" }",
+      "let i = Int64(Swift.abs(n).truncatingRemainder(dividingBy: 100))",
+      "if i % 10 == 1 && i != 11 { return \"one\" }",
+      "if (2...4).contains(i % 10) && !(12...14).contains(i) { return \"few\" }",
+      "return \"many\"",
+    ],
+    PluralRule::Polish => &[
+      "if n.truncatingRemainder(dividingBy: 1) != 0 { return \"other\" }",
+      "if Swift.abs(n) == 1 { return \"one\" }",
+      "let i = Int64(Swift.abs(n).truncatingRemainder(dividingBy: 100))",
+      "if (2...4).contains(i % 10) && !(12...14).contains(i) { return \"few\" }",
+      "return \"many\"",
+    ],
+    PluralRule::Czech => &[
+      "if n.truncatingRemainder(dividingBy: 1) != 0 { return \"many\" }",
+      "if n == 1 { return \"one\" }",
+      "if (2...4).contains(n) { return \"few\" }",
+      "return \"other\"",
+    ],
+    PluralRule::Arabic => &[
+      "if n.truncatingRemainder(dividingBy: 1) != 0 { return \"other\" }",
+      "let i = Int64(Swift.abs(n).truncatingRemainder(dividingBy: 100))",
+      "if Swift.abs(n) <= 2 { return i == 0 ? \"zero\" : i == 1 ? \"one\" : \"two\" }",
+      "if (3...10).contains(i) { return \"few\" }",
+      "if i >= 11 { return \"many\" }",
+      "return \"other\"",
+    ],
+  }
+}
+
+fn swift_type(type_: &InterpolationType) -> &'static str {
+  match type_ {
+    InterpolationType::None
+    | InterpolationType::String
+    | InterpolationType::Enum(_)
+    | InterpolationType::Custom(_) => "String",
+    InterpolationType::Number => "Double",
+    InterpolationType::Boolean => "Bool",
+  }
+}
+
+fn ident(key: &Key) -> String {
+  escape_keyword(&key.sanitized, KEYWORDS)
+}
+
+/// Converts a locale like `pt-BR` into an enum case like `ptBr`
+fn case_name(locale: &Locale) -> String {
+  let name = locale_ident(locale);
+  let mut chars = name.chars();
+  let name = chars
+    .next()
+    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+    .unwrap_or_default();
+
+  escape_keyword(&name, KEYWORDS)
+}
+
+/// Escapes text for a Swift string literal
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(result, "\\u{{{:x}}}", c as u32);
+      }
+      c => result.push(c),
+    }
+  }
+
+  result
+}
+
+/// Quotes a string as a Swift string literal
+fn string(s: &str) -> String {
+  format!("\"{}\"", escape(s))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  #[test]
+  fn swift_enum() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello, {name}! {{Braces} and \"quotes\" with \\(parens)"
+items = "{count:number -> 0 {No items} one {One item} *other {{count} items}}"
+reply = "{gender -> female {She} *other {They}} replied to {guard}"
+only_english = "Only in English"
+
+[nested.module]
+storage = "{used:number} of {total:number}"
+"#,
+      ),
+      (
+        "is",
+        r#"
+greeting = "Halló, {name}!"
+items = "{count:number -> one {{count} hlutur} *other {{count} hlutir}}"
+reply = "{gender -> female {Hún} *other {Þau}} svöruðu {guard}"
+
+[nested.module]
+storage = "{used:number} af {total:number}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("en".to_string()), Locale("is".to_string())];
+    insta::assert_snapshot!(write_file(&locales, &module));
+  }
+
+  #[test]
+  fn optional_args() {
+    let module = module(&[
+      (
+        "en",
+        r#"
+greeting = "Hello {name=there}{suffix?}"
+items = "{count:number=No} items"
+signature = "Best, {sender=the team}"
+"#,
+      ),
+      (
+        "de",
+        r#"
+greeting = "Hallo {name=du}{suffix?}"
+items = "{count:number=Keine} Einträge"
+signature = "Viele Grüße, {sender}"
+"#,
+      ),
+    ]);
+
+    let locales = [Locale("de".to_string()), Locale("en".to_string())];
+    let mut out = String::new();
+    write_namespaces(&mut out, 0, &module, &Swift { locales: &locales });
+    insta::assert_snapshot!(out);
+  }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, iter::Peekable, str::CharIndices};
 
 use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
@@ -8,26 +8,74 @@ use crate::{
   sanitize::is_valid_identifier,
 };
 
+/// An interpolation of a message, with its position in each locale's translation
 #[derive(Debug, Default, Clone)]
 pub struct Interpolation {
   pub type_: InterpolationType,
   pub ranges: HashMap<Locale, (usize, usize)>,
+  /// Whether any occurrence of the interpolation has no default, so its argument can't be omitted
+  pub required: bool,
 }
 
+/// An interpolation found in a translation, e.g. `{name}` or `{count:number -> ...}`
 #[derive(Debug)]
 pub struct ParsedInterpolation {
   pub type_: InterpolationType,
   pub name: String,
   pub start: usize,
   pub end: usize,
+  /// Text used if the argument is omitted, e.g. `there` in `{name=there}`, or empty for
+  /// `{name?}`. Required interpolations have none.
+  pub default: Option<String>,
+  /// Variants of a select expression, empty for plain interpolations
+  pub variants: Vec<ParsedVariant>,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// A variant of a select expression found in a translation
+#[derive(Debug)]
+pub struct ParsedVariant {
+  pub key: String,
+  pub default: bool,
+  /// Byte range of the variant body, excluding its braces
+  pub start: usize,
+  pub end: usize,
+  pub interpolations: Vec<ParsedInterpolation>,
+}
+
+/// A reference to another message found in a translation, e.g. `{@common.brand}`
+#[derive(Debug)]
+pub struct ParsedReference {
+  /// Key path of the referenced message, joined by dots
+  pub path: String,
+  pub start: usize,
+  pub end: usize,
+}
+
+/// The type of an interpolation, e.g. `number` in `{count:number}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum InterpolationType {
   #[default]
   None,
   String,
   Number,
+  Boolean,
+  /// One of the given cases, e.g. `enum(free|pro|team)`
+  Enum(Vec<String>),
+  Custom(CustomType),
+}
+
+/// A type declared by the user, e.g. `money` in `{total:money}`, whose values are formatted by a
+/// function of the user's code
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomType {
+  pub name: String,
+  /// The TypeScript type of the values, e.g. `Money`
+  pub type_name: String,
+  /// A function taking a value and the locale, e.g. `formatMoney`
+  pub formatter: String,
+  /// The module exporting the type and formatter. Paths starting with `.` are relative to the
+  /// output directory.
+  pub module: String,
 }
 
 impl TryFrom<&str> for InterpolationType {
@@ -37,7 +85,27 @@ impl TryFrom<&str> for InterpolationType {
     match value {
       "string" => Ok(Self::String),
       "number" => Ok(Self::Number),
-      _ => Err(()),
+      "boolean" => Ok(Self::Boolean),
+      _ => {
+        let cases = value
+          .strip_prefix("enum(")
+          .and_then(|cases| cases.strip_suffix(')'))
+          .ok_or(())?
+          .split('|')
+          .map(|case| case.trim().to_string())
+          .collect::<Vec<_>>();
+
+        let valid = cases
+          .iter()
+          .enumerate()
+          .all(|(i, case)| is_variant_key(case) && !cases[..i].contains(case));
+
+        if valid {
+          Ok(Self::Enum(cases))
+        } else {
+          Err(())
+        }
+      }
     }
   }
 }
@@ -48,16 +116,49 @@ impl std::fmt::Display for InterpolationType {
       Self::None => write!(f, "none"),
       Self::String => write!(f, "string"),
       Self::Number => write!(f, "number"),
+      Self::Boolean => write!(f, "boolean"),
+      Self::Enum(cases) => write!(f, "enum({})", cases.join("|")),
+      Self::Custom(custom) => write!(f, "{}", custom.name),
     }
   }
 }
 
 impl InterpolationType {
-  pub fn as_typescript_type(&self) -> &'static str {
+  /// The type an interpolation has when it's used with both types, e.g. in different locales.
+  /// Untyped interpolations unify with any declared type, and enums with the same cases in any
+  /// order.
+  pub fn unify(self, other: Self) -> Option<Self> {
+    match (self, other) {
+      (Self::None, type_) | (type_, Self::None) => Some(type_),
+      (Self::Enum(a), Self::Enum(b)) => {
+        (a.len() == b.len() && a.iter().all(|case| b.contains(case))).then_some(Self::Enum(a))
+      }
+      (a, b) if a == b => Some(a),
+      _ => None,
+    }
+  }
+
+  /// The values a select on this type can match, if they're limited
+  pub fn cases(&self) -> Option<Vec<&str>> {
+    match self {
+      Self::Boolean => Some(vec!["true", "false"]),
+      Self::Enum(cases) => Some(cases.iter().map(String::as_str).collect()),
+      Self::None | Self::String | Self::Number | Self::Custom(_) => None,
+    }
+  }
+
+  pub fn as_typescript_type(&self) -> String {
     match self {
-      Self::None => "string",
-      Self::String => "string",
-      Self::Number => "number",
+      Self::None => "string".to_string(),
+      Self::String => "string".to_string(),
+      Self::Number => "number".to_string(),
+      Self::Boolean => "boolean".to_string(),
+      Self::Enum(cases) => cases
+        .iter()
+        .map(|case| format!("\"{case}\""))
+        .collect::<Vec<_>>()
+        .join(" | "),
+      Self::Custom(custom) => custom.type_name.clone(),
     }
   }
 }
@@ -89,160 +190,558 @@ pub enum InterpolationParseError {
   #[error("Invalid interpolation type")]
   #[diagnostic(
     code(interpolation::unsupported_type),
-    help = "Choose a valid interpolation type"
+    help = "Supported types are `string`, `number`, `boolean` and enums like `enum(free|pro)`"
   )]
   InvalidType {
     #[label("This type is not supported")]
     at: SourceSpan,
     type_: String,
   },
+
+  #[error("Invalid select variant")]
+  #[diagnostic(
+    code(interpolation::invalid_variant),
+    help = "Variants must look like `key {{text}}`, e.g. `{{count:number -> one {{One item}} *other {{Many items}}}}`"
+  )]
+  InvalidVariant(#[label("Expected a variant key followed by its text in braces")] SourceSpan),
+
+  #[error("Select is missing a default variant")]
+  #[diagnostic(
+    code(interpolation::missing_default),
+    help = "Mark exactly one variant as the default using `*`, e.g. `*other {{...}}`"
+  )]
+  MissingDefaultVariant(#[label("No default variant")] SourceSpan),
+
+  #[error("Select has multiple default variants")]
+  #[diagnostic(
+    code(interpolation::multiple_defaults),
+    help = "Only one variant can be marked as the default using `*`"
+  )]
+  MultipleDefaultVariants(#[label("Second default variant")] SourceSpan),
+
+  #[error("Invalid optional interpolation")]
+  #[diagnostic(
+    code(interpolation::invalid_optional),
+    help = "Optional interpolations end with `?`, e.g. `{{name?}}`, or with a default after `=`, e.g. `{{name=there}}`"
+  )]
+  InvalidOptional(#[label("Expected the interpolation to end here")] SourceSpan),
+
+  #[error("Selects can't have defaults")]
+  #[diagnostic(
+    code(interpolation::select_default),
+    help = "The argument of a select is always required, remove the `?` or the default after `=`"
+  )]
+  SelectDefault(#[label("Default of a select")] SourceSpan),
+
+  #[error("Invalid message reference")]
+  #[diagnostic(
+    code(interpolation::invalid_reference),
+    help = "References are key paths of other messages joined by dots, e.g. `{{@common.brand}}`"
+  )]
+  InvalidReference(#[label("Not a key path")] SourceSpan),
 }
 
 // TODO: Smallvecs?
 #[derive(Debug, Default)]
 pub struct ParsedInterpolations {
   pub interpolations: Vec<ParsedInterpolation>,
+  /// References to other messages, including the ones nested inside select variants
+  pub references: Vec<ParsedReference>,
   pub errors: Vec<InterpolationParseError>,
 }
 
+impl ParsedInterpolations {
+  /// Returns all interpolations, including the ones nested inside select variants
+  pub fn flattened(&self) -> Vec<&ParsedInterpolation> {
+    fn collect<'a>(
+      interpolations: &'a [ParsedInterpolation],
+      out: &mut Vec<&'a ParsedInterpolation>,
+    ) {
+      for interpolation in interpolations {
+        out.push(interpolation);
+        for variant in &interpolation.variants {
+          collect(&variant.interpolations, out);
+        }
+      }
+    }
+
+    let mut result = Vec::with_capacity(self.interpolations.len());
+    collect(&self.interpolations, &mut result);
+    result
+  }
+}
+
 pub fn parse_interpolations(translation: &Translation) -> ParsedInterpolations {
-  let mut result = ParsedInterpolations::default();
-  let s = &translation.0;
+  parse_str(&translation.0)
+}
+
+/// Parses interpolations in any string, e.g. an unescaped translation. Ranges are byte offsets into
+/// the given string.
+pub fn parse_str(s: &str) -> ParsedInterpolations {
+  parse_str_with_types(s, &[])
+}
 
+/// Parses interpolations like [`parse_str`], additionally accepting the given custom types
+pub fn parse_str_with_types(s: &str, custom_types: &[CustomType]) -> ParsedInterpolations {
   if !s.contains('{') {
-    return result;
+    return ParsedInterpolations::default();
+  }
+
+  let mut parser = Parser {
+    len: s.len(),
+    chars: s.char_indices().peekable(),
+    references: Vec::new(),
+    errors: Vec::new(),
+    custom_types,
+  };
+
+  let (interpolations, _) = parser.parse(false);
+
+  ParsedInterpolations {
+    interpolations,
+    references: parser.references,
+    errors: parser.errors,
   }
+}
 
-  let mut parsing_interpolation = false;
-  let mut start_byte_index = 0;
-  let mut parsing_type = false;
-  let mut current_name = String::new();
-  let mut current_type = String::new();
+struct Parser<'a> {
+  len: usize,
+  chars: Peekable<CharIndices<'a>>,
+  references: Vec<ParsedReference>,
+  errors: Vec<InterpolationParseError>,
+  custom_types: &'a [CustomType],
+}
 
-  let mut chars = s.char_indices().peekable();
+impl Parser<'_> {
+  fn resolve_type(&self, name: &str) -> Result<InterpolationType, ()> {
+    InterpolationType::try_from(name).or_else(|()| {
+      self
+        .custom_types
+        .iter()
+        .find(|custom| custom.name == name)
+        .map(|custom| InterpolationType::Custom(custom.clone()))
+        .ok_or(())
+    })
+  }
 
-  while let Some((byte_index, c)) = chars.next() {
-    if c == '{' {
-      // Check if this is an escape sequence {{
-      if chars.peek().is_some_and(|&(_, next_char)| next_char == '{') {
-        // Skip the escape sequence
-        chars.next();
+  /// Parses interpolations until the end of the input. If `nested` is set, this stops at the first
+  /// closing brace that is not part of an interpolation and returns its byte index, which is used
+  /// to parse the bodies of select variants.
+  fn parse(&mut self, nested: bool) -> (Vec<ParsedInterpolation>, Option<usize>) {
+    let mut result = Vec::new();
+    let mut parsing_interpolation = false;
+    let mut start_byte_index = 0;
+    let mut parsing_type = false;
+    let mut current_name = String::new();
+    let mut current_type = String::new();
+    let mut current_default: Option<String> = None;
+    let mut default_start = 0;
+
+    while let Some((byte_index, c)) = self.chars.next() {
+      // Defaults are literal text up to the closing brace, or the arrow of a select
+      if let Some(default) = &mut current_default
+        && c != '}'
+        && !(c == '-' && self.chars.peek().is_some_and(|&(_, c)| c == '>'))
+      {
+        default.push(c);
         continue;
       }
 
-      if parsing_interpolation {
-        // We're already parsing an interpolation and found another opening brace
-        // This indicates nested braces, which is invalid
+      if c == '{' {
+        // Check if this is an escape sequence {{
+        if self
+          .chars
+          .peek()
+          .is_some_and(|&(_, next_char)| next_char == '{')
+        {
+          // Skip the escape sequence
+          self.chars.next();
+          continue;
+        }
+
+        if parsing_interpolation {
+          // We're already parsing an interpolation and found another opening brace
+          // This indicates nested braces, which is invalid
+
+          // Skip until we hit the next closing brace, so we can keep parsing
+          let mut offset = 0;
+          while self.chars.peek().is_some_and(|&(_, c)| c != '}') {
+            offset += 1;
+            self.chars.next();
+          }
+          self.chars.next();
+
+          parsing_interpolation = false;
+          parsing_type = false;
+          current_name.clear();
+
+          self.errors.push(InterpolationParseError::InvalidIdentifier(
+            (start_byte_index + 1..byte_index + offset).into(),
+          ));
+          continue;
+        }
+
+        start_byte_index = byte_index;
+        parsing_interpolation = true;
+        continue;
+      }
 
-        // Skip until we hit the next closing brace, so we can keep parsing
-        let mut offset = 0;
-        while chars.peek().is_some_and(|&(_, c)| c != '}') {
-          offset += 1;
-          chars.next();
+      if !parsing_interpolation {
+        if nested && c == '}' {
+          return (result, Some(byte_index));
         }
 
+        continue;
+      }
+
+      if c == '@' && current_name.is_empty() && !parsing_type {
+        // A reference to another message, e.g. `{@common.brand}`
+        parsing_interpolation = false;
+        self.parse_reference(start_byte_index);
+        continue;
+      }
+
+      if c == '-' && self.chars.peek().is_some_and(|&(_, c)| c == '>') {
+        // This is a select expression, e.g. `{count:number -> one {...} *other {...}}`
+        self.chars.next();
+
+        let name = current_name.trim_end().to_string();
+        let type_ = current_type.trim_end().to_string();
+        let had_type = parsing_type;
+
         parsing_interpolation = false;
         parsing_type = false;
         current_name.clear();
+        current_type.clear();
 
-        result
-          .errors
-          .push(InterpolationParseError::InvalidIdentifier(
-            (start_byte_index + 1..byte_index + offset).into(),
+        if let Some(default) = current_default.take() {
+          self.errors.push(InterpolationParseError::SelectDefault(
+            (default_start, default.trim_end().len() + 1).into(),
           ));
+          self.skip_select(1);
+          continue;
+        }
+
+        if !had_type && let Err(err) = validate_interpolation_name(start_byte_index, &name) {
+          self.errors.push(err);
+          self.skip_select(1);
+          continue;
+        }
+
+        let type_ = if had_type {
+          match self.resolve_type(&type_) {
+            Ok(t) => t,
+            Err(()) => {
+              self.errors.push(InterpolationParseError::InvalidType {
+                at: (start_byte_index + name.len() + 2..byte_index).into(),
+                type_,
+              });
+              self.skip_select(1);
+              continue;
+            }
+          }
+        } else {
+          InterpolationType::None
+        };
+
+        match self.parse_variants(start_byte_index) {
+          Ok((variants, end)) => result.push(ParsedInterpolation {
+            name,
+            start: start_byte_index,
+            end,
+            type_,
+            default: None,
+            variants,
+          }),
+          Err(err) => self.errors.push(err),
+        }
+
         continue;
       }
 
-      start_byte_index = byte_index;
-      parsing_interpolation = true;
-      continue;
-    }
+      if c == ':' {
+        if let Err(err) = validate_interpolation_name(start_byte_index, &current_name) {
+          // Skip until we hit the next closing brace, so we can keep parsing
+          while self.chars.peek().is_some_and(|&(_, c)| c != '}') {
+            self.chars.next();
+          }
+          self.chars.next();
 
-    if !parsing_interpolation {
-      continue;
-    }
+          self.errors.push(err);
+          parsing_interpolation = false;
+          current_name.clear();
+          continue;
+        };
 
-    if c == ':' {
-      if let Err(err) = validate_interpolation_name(start_byte_index, &current_name) {
-        // Skip until we hit the next closing brace, so we can keep parsing
-        while chars.peek().is_some_and(|&(_, c)| c != '}') {
-          chars.next();
+        parsing_type = true;
+        continue;
+      }
+
+      if c == '?' || c == '=' {
+        // An optional interpolation, e.g. `{name?}` or `{name=there}`
+        if c == '?' && self.chars.peek().is_some_and(|&(_, c)| c != '}') && !self.at_select() {
+          let mut end = byte_index + 1;
+          for (index, c) in self.chars.by_ref() {
+            if c == '}' {
+              break;
+            }
+
+            end = index + c.len_utf8();
+          }
+
+          self.errors.push(InterpolationParseError::InvalidOptional(
+            (byte_index + 1..end).into(),
+          ));
+          parsing_interpolation = false;
+          parsing_type = false;
+          current_name.clear();
+          current_type.clear();
+          continue;
         }
 
-        result.errors.push(err);
+        current_default = Some(String::new());
+        default_start = byte_index;
+        continue;
+      }
+
+      if c == '}' {
+        // This is the end of the interpolation
+        let typename = if !current_type.is_empty() {
+          let type_ = match self.resolve_type(&current_type) {
+            Ok(t) => t,
+            Err(()) => {
+              self.errors.push(InterpolationParseError::InvalidType {
+                at: (start_byte_index + current_name.len() + 2..byte_index).into(),
+                type_: current_type.clone(),
+              });
+
+              parsing_interpolation = false;
+              parsing_type = false;
+              current_name.clear();
+              current_type.clear();
+              current_default = None;
+              continue;
+            }
+          };
+
+          current_type.clear();
+          type_
+        } else {
+          // Only validate if we haven't already done so (when no type was specified)
+          match validate_interpolation_name(start_byte_index, &current_name) {
+            Ok(_) => InterpolationType::None,
+            Err(err) => {
+              parsing_interpolation = false;
+              parsing_type = false;
+              current_name.clear();
+              current_default = None;
+              self.errors.push(err);
+              continue;
+            }
+          }
+        };
+
+        result.push(ParsedInterpolation {
+          name: current_name.clone(),
+          start: start_byte_index,
+          end: byte_index,
+          type_: typename,
+          default: current_default.take(),
+          variants: Vec::new(),
+        });
+
         parsing_interpolation = false;
+        parsing_type = false;
         current_name.clear();
         continue;
-      };
+      }
+
+      if parsing_type {
+        current_type.push(c);
+        continue;
+      }
 
-      parsing_type = true;
-      continue;
+      current_name.push(c);
     }
 
-    if c == '}' {
-      // This is the end of the interpolation
-      let typename = if !current_type.is_empty() {
-        let type_ = match InterpolationType::try_from(current_type.as_str()) {
-          Ok(t) => t,
-          Err(()) => {
-            result.errors.push(InterpolationParseError::InvalidType {
-              at: (start_byte_index + current_name.len() + 2..byte_index).into(),
-              type_: current_type.clone(),
-            });
-
-            parsing_interpolation = false;
-            parsing_type = false;
-            current_name.clear();
-            current_type.clear();
-            continue;
-          }
-        };
+    if parsing_interpolation {
+      // Unclosed interpolation
+      self.errors.push(InterpolationParseError::Unclosed(
+        (start_byte_index + 1..self.len).into(),
+      ));
+    }
 
-        current_type.clear();
-        type_
-      } else {
-        // Only validate if we haven't already done so (when no type was specified)
-        match validate_interpolation_name(start_byte_index, &current_name) {
-          Ok(_) => InterpolationType::None,
-          Err(err) => {
-            parsing_interpolation = false;
-            parsing_type = false;
-            current_name.clear();
-            result.errors.push(err);
-            continue;
-          }
-        }
+    (result, None)
+  }
+
+  /// Parses the variants of a select expression, right after the `->`. Returns the variants and the
+  /// byte index of the closing brace of the select.
+  fn parse_variants(
+    &mut self,
+    start: usize,
+  ) -> Result<(Vec<ParsedVariant>, usize), InterpolationParseError> {
+    let mut variants: Vec<ParsedVariant> = Vec::new();
+
+    loop {
+      self.skip_whitespace();
+
+      let Some((index, c)) = self.chars.next() else {
+        return Err(InterpolationParseError::Unclosed(
+          (start + 1..self.len).into(),
+        ));
       };
 
-      result.interpolations.push(ParsedInterpolation {
-        name: current_name.clone(),
-        start: start_byte_index,
-        end: byte_index,
-        type_: typename,
-      });
-
-      parsing_interpolation = false;
-      parsing_type = false;
-      current_name.clear();
-      continue;
+      if c == '}' {
+        if !variants.iter().any(|v| v.default) {
+          return Err(InterpolationParseError::MissingDefaultVariant(
+            (start..index + 1).into(),
+          ));
+        }
+
+        return Ok((variants, index));
+      }
+
+      if c != '*' && !is_variant_key_char(c) {
+        // The offending character might have opened another brace
+        self.skip_select(if c == '{' { 2 } else { 1 });
+        return Err(InterpolationParseError::InvalidVariant((index, 1).into()));
+      }
+
+      let default = c == '*';
+      let mut key = String::new();
+      if !default {
+        key.push(c);
+      }
+
+      while let Some(&(_, c)) = self.chars.peek() {
+        if !is_variant_key_char(c) {
+          break;
+        }
+
+        key.push(c);
+        self.chars.next();
+      }
+
+      if key.is_empty() {
+        self.skip_select(1);
+        return Err(InterpolationParseError::InvalidVariant((index, 1).into()));
+      }
+
+      if default && variants.iter().any(|v| v.default) {
+        self.skip_select(1);
+        return Err(InterpolationParseError::MultipleDefaultVariants(
+          (index, key.len() + 1).into(),
+        ));
+      }
+
+      self.skip_whitespace();
+
+      match self.chars.next() {
+        Some((body_start, '{')) => {
+          let (interpolations, end) = self.parse(true);
+          let Some(end) = end else {
+            return Err(InterpolationParseError::Unclosed(
+              (start + 1..self.len).into(),
+            ));
+          };
+
+          variants.push(ParsedVariant {
+            key,
+            default,
+            start: body_start + 1,
+            end,
+            interpolations,
+          });
+        }
+        Some((index, '}')) => {
+          return Err(InterpolationParseError::InvalidVariant((index, 1).into()));
+        }
+        Some((index, _)) => {
+          self.skip_select(1);
+          return Err(InterpolationParseError::InvalidVariant((index, 1).into()));
+        }
+        None => {
+          return Err(InterpolationParseError::Unclosed(
+            (start + 1..self.len).into(),
+          ));
+        }
+      }
     }
+  }
+
+  /// Whether the next characters are the arrow of a select, after optional whitespace
+  fn at_select(&self) -> bool {
+    let mut rest = self
+      .chars
+      .clone()
+      .map(|(_, c)| c)
+      .skip_while(|c| c.is_whitespace());
+
+    rest.next() == Some('-') && rest.next() == Some('>')
+  }
+
+  /// Parses the key path of a reference, right after the `@`
+  fn parse_reference(&mut self, start: usize) {
+    let mut path = String::new();
+    let mut end = None;
+
+    for (index, c) in self.chars.by_ref() {
+      if c == '}' {
+        end = Some(index);
+        break;
+      }
 
-    if parsing_type {
-      current_type.push(c);
-      continue;
+      path.push(c);
     }
 
-    current_name.push(c);
+    let Some(end) = end else {
+      self.errors.push(InterpolationParseError::Unclosed(
+        (start + 1..self.len).into(),
+      ));
+      return;
+    };
+
+    let is_key_path = path
+      .split('.')
+      .all(|key| !key.is_empty() && !key.contains(|c: char| c == '{' || c.is_whitespace()));
+
+    if is_key_path {
+      self.references.push(ParsedReference { path, start, end });
+    } else {
+      self.errors.push(InterpolationParseError::InvalidReference(
+        (start + 2..end).into(),
+      ));
+    }
   }
 
-  if parsing_interpolation {
-    // Unclosed interpolation
-    result.errors.push(InterpolationParseError::Unclosed(
-      (start_byte_index + 1..s.len()).into(),
-    ));
+  fn skip_whitespace(&mut self) {
+    while self.chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+      self.chars.next();
+    }
+  }
+
+  /// Skips the rest of a select expression after an error, so we can keep parsing
+  fn skip_select(&mut self, mut depth: usize) {
+    for (_, c) in self.chars.by_ref() {
+      match c {
+        '{' => depth += 1,
+        '}' => depth -= 1,
+        _ => {}
+      }
+
+      if depth == 0 {
+        return;
+      }
+    }
   }
+}
+
+fn is_variant_key(key: &str) -> bool {
+  !key.is_empty() && key.chars().all(is_variant_key_char)
+}
 
-  result
+fn is_variant_key_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
 }
 
 /// Validates that an interpolation identifier follows the rules:
@@ -342,4 +841,60 @@ mod tests {
       parse("{{{{"),
     ]);
   }
+
+  #[test]
+  fn select_expressions() {
+    insta::assert_debug_snapshot!([
+      parse("{tier -> free {Free plan} *other {Paid plan}}"),
+      parse("{count:number -> 0 {No items} one {One item} *other {{count} items}}"),
+      parse("Hi {name}, {gender -> male {he} *other {they}} said"),
+      parse("{a -> x {{b -> y {nested} *z {{c}}}} *w {}}"),
+    ]);
+  }
+
+  #[test]
+  fn invalid_select_expressions() {
+    insta::assert_debug_snapshot!([
+      parse("{tier -> free {Free plan} other {Paid plan}}"),
+      parse("{tier -> *free {Free} *other {Paid}} {after}"),
+      parse("{tier -> free Free *other {Paid}} {after}"),
+      parse("{tier -> free {Free} *other {Paid"),
+      parse("{1tier -> *other {Paid}} {after}"),
+      parse("{tier:float -> *other {Paid}} {after}"),
+      parse("{tier -> {Free} *other {Paid}} {after}"),
+    ]);
+  }
+
+  #[test]
+  fn boolean_and_enum_types() {
+    insta::assert_debug_snapshot!([
+      parse("{flag:boolean} {tier:enum(free|pro|team)} {spaced:enum( a | b )}"),
+      parse("{flag:boolean -> true {Yes} *false {No}}"),
+      parse("{tier:enum(free|pro) -> free {Free} *other {Paid}}"),
+      parse("{a:enum()} {b:enum(x|x)} {c:enum(x y)} {d:enum(x|)} {e:enum}"),
+    ]);
+  }
+
+  #[test]
+  fn optional_interpolations() {
+    insta::assert_debug_snapshot!([
+      parse("Hello {name?}, {greeting=good morning}!"),
+      parse("{count:number=0} items, {label:string?} and {empty=}"),
+      parse("{tier -> free {Hi {name=there}} *other {Welcome}}"),
+      parse("{name=a:b -> c}"),
+      parse("{n:number=0 -> one {One} *other {{n}}} {n?}"),
+      parse("{gender? -> female {She} *other {They}} {name=a-b>c}"),
+      parse("{name?x} {=there} {first name=there}"),
+      parse("{name=unclosed"),
+    ]);
+  }
+
+  #[test]
+  fn message_references() {
+    insta::assert_debug_snapshot!([
+      parse("Welcome to {@common.brand}, {name}!"),
+      parse("{tier -> free {{@brand} Free} *other {{@brand} Pro}}"),
+      parse("{@} {@common.} {@common brand} {@brand"),
+    ]);
+  }
 }
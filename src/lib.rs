@@ -0,0 +1,69 @@
+//! Generates type-safe message functions from TOML or Fluent translation files.
+//!
+//! The translation files of a directory are collected into a [`Module`] tree, which can then be
+//! passed to one of the generators in [`generate`], or exported for translation tools using
+//! [`po`], [`xliff`], [`csv`] or [`arb`]. Problems found in the translation files don't abort the
+//! build, they're returned as [`Diagnostics`] instead.
+//!
+//! This makes it possible to generate messages from a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!   println!("cargo::rerun-if-changed=locales");
+//!
+//!   let result = woof::collect_and_build_modules("locales")?;
+//!   for line in result.diagnostics.render().lines() {
+//!     println!("cargo::warning={line}");
+//!   }
+//!
+//!   // Generators replace the output directory, so build scripts only write into `OUT_DIR`
+//!   let out = Path::new(&std::env::var("OUT_DIR")?).join("messages");
+//!   woof::generate::rust::generate(&out, &result.locales, &result.module)?;
+//!   Ok(())
+//! }
+//! ```
+//!
+//! The generated module is then included into the crate:
+//!
+//! ```ignore
+//! mod messages {
+//!   include!(concat!(env!("OUT_DIR"), "/messages/mod.rs"));
+//! }
+//! ```
+
+mod collect;
+mod context;
+mod errors;
+mod fluent;
+mod parse;
+mod sanitize;
+
+/// Flutter ARB files with ICU messages
+pub mod arb;
+/// CSV spreadsheets with one column per locale
+pub mod csv;
+/// Generators for TypeScript, JavaScript, Rust, Python, Go, Kotlin, Swift and string resources
+pub mod generate;
+/// Parsing of interpolations and select expressions in translations
+pub mod interpolations;
+/// gettext PO files
+pub mod po;
+/// Writing translations back into TOML files
+pub mod update;
+/// XLIFF 2.0 files
+pub mod xliff;
+
+pub use collect::{
+  FileMode, ModuleBuildResult, collect_and_build_modules, collect_and_build_modules_with,
+  detect_file_mode,
+};
+pub use context::{Diagnostics, KeyDiagnostic};
+pub use errors::WoofError;
+pub use interpolations::CustomType;
+pub use parse::{
+  BuildOptions, DEFAULT_LOCALE, Key, LengthLimits, Locale, Message, Metadata, Module, Part,
+  Translation, Variant,
+};
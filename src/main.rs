@@ -1,33 +1,474 @@
-mod collect;
-mod context;
-mod errors;
-mod generate;
-mod interpolations;
-mod parse;
-mod sanitize;
-
-use clap::Parser;
-use errors::WoofError;
-use std::path::Path;
+use clap::{Parser, Subcommand, ValueEnum};
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+use woof::{
+  BuildOptions, CustomType, LengthLimits, Locale, WoofError, arb, collect_and_build_modules_with,
+  csv, generate, po, xliff,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+  #[command(subcommand)]
+  command: Option<Command>,
+
   /// Output directory for generated files
   #[arg(short, long, default_value = "messages")]
   out: String,
 
+  /// Platform to generate code or resources for
+  #[arg(short, long, value_enum, default_value_t = Target::Typescript)]
+  target: Target,
+
+  /// Separator for joining key paths into names on native platforms. Defaults to `_` for Android
+  /// and `.` for iOS
+  #[arg(long)]
+  separator: Option<String>,
+
+  /// How generated TypeScript modules import each other
+  #[arg(long, value_enum, default_value_t = ImportStyle::Bundler)]
+  import_style: ImportStyle,
+
+  /// Write `.mts` instead of `.ts` files
+  #[arg(long)]
+  mts: bool,
+
+  /// Write TypeScript into a single file at the output path instead of a directory
+  #[arg(long)]
+  single_file: bool,
+
+  /// Split TypeScript translations into a module per locale, loaded with `loadLocale`
+  #[arg(long, conflicts_with = "single_file")]
+  lazy: bool,
+
+  /// Look up TypeScript translations with the smallest of `switch` statements, records or
+  /// if-chains
+  #[arg(long, conflicts_with = "lazy")]
+  lookup_tables: bool,
+
+  /// Package of the generated Kotlin file
+  #[arg(long)]
+  package: Option<String>,
+
+  #[command(flatten)]
+  build: BuildArgs,
+
   /// Input directory containing translation files
-  input_dir: String,
+  #[arg(required = true)]
+  input_dir: Option<String>,
+}
+
+/// Options for building the messages, shared by code generation, exports and imports
+#[derive(clap::Args)]
+struct BuildArgs {
+  /// Maximum length of the translations of a message or all messages in a module, e.g.
+  /// `buttons=20`. A `maxLength` in the message's metadata takes precedence.
+  #[arg(long, value_name = "KEY_PATH=LENGTH", value_parser = parse_max_length)]
+  max_length: Vec<(String, usize)>,
+
+  /// Number of characters each interpolation is counted as when checking maximum lengths
+  #[arg(long, default_value_t = 0)]
+  placeholder_width: usize,
+
+  /// Interpolation type formatted by a function of your code, e.g.
+  /// `money=Money,formatMoney,./money` for `{total:money}`. Only TypeScript and JavaScript use
+  /// the formatter, other targets take these values as strings.
+  #[arg(long, value_name = "NAME=TYPE,FORMATTER,MODULE", value_parser = parse_custom_type)]
+  custom_type: Vec<CustomType>,
+}
+
+impl BuildArgs {
+  fn options(self) -> BuildOptions {
+    BuildOptions {
+      limits: LengthLimits {
+        max_lengths: self.max_length.into_iter().collect(),
+        placeholder_width: self.placeholder_width,
+      },
+      custom_types: self.custom_type,
+    }
+  }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Target {
+  /// TypeScript modules with one function per message
+  Typescript,
+  /// ES modules with TypeScript declarations, for use without a build step
+  Javascript,
+  /// `strings.xml` resources in `values-<locale>` directories
+  Android,
+  /// `Localizable.strings` files in `<locale>.lproj` directories
+  Ios,
+  /// A Rust module tree with one function per message
+  Rust,
+  /// A Python package with one function per message
+  Python,
+  /// A Go package per top-level module with one function per message
+  Go,
+  /// A Kotlin object with nested objects per module
+  Kotlin,
+  /// A Swift enum with nested enums per module
+  Swift,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportStyle {
+  /// Directory imports like `./about`, for bundlers
+  Bundler,
+  /// Fully specified `.js` paths, for `moduleResolution: NodeNext`
+  NodeNext,
+  /// Fully specified `.ts` paths, for Deno
+  Deno,
+}
+
+impl From<ImportStyle> for generate::ImportStyle {
+  fn from(style: ImportStyle) -> Self {
+    match style {
+      ImportStyle::Bundler => Self::Bundler,
+      ImportStyle::NodeNext => Self::NodeNext,
+      ImportStyle::Deno => Self::Deno,
+    }
+  }
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Export translations for translation tools
+  #[command(subcommand)]
+  Export(ExportFormat),
+
+  /// Import translations from translation tools
+  #[command(subcommand)]
+  Import(ImportFormat),
+}
+
+#[derive(Subcommand)]
+enum ExportFormat {
+  /// Export one gettext PO file per locale and a POT template
+  Po {
+    /// Output directory for PO files
+    #[arg(short, long, default_value = "po")]
+    out: String,
+
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+  },
+
+  /// Export a CSV file with one row per message and one column per locale
+  Csv {
+    /// Output file
+    #[arg(short, long, default_value = "messages.csv")]
+    out: String,
+
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+  },
+
+  /// Export an XLIFF 2.0 file for translating from one locale into another
+  Xliff {
+    /// Locale to translate from
+    #[arg(short, long, default_value = woof::DEFAULT_LOCALE)]
+    source: String,
+
+    /// Locale to translate into
+    #[arg(short, long)]
+    target: String,
+
+    /// Output file, defaults to `<target>.xlf`
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+  },
+
+  /// Export one Flutter ARB file per locale
+  Arb {
+    /// Output directory for ARB files
+    #[arg(short, long, default_value = "l10n")]
+    out: String,
+
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+  },
+}
+
+#[derive(Subcommand)]
+enum ImportFormat {
+  /// Import translated gettext PO files into the translation files
+  Po {
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// PO files to import
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+  },
+
+  /// Import changed cells of a CSV file into the translation files
+  Csv {
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// CSV file to import
+    file: PathBuf,
+  },
+
+  /// Import translated XLIFF 2.0 files into the translation files
+  Xliff {
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// XLIFF files to import
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+  },
+
+  /// Import translated Flutter ARB files into the translation files
+  Arb {
+    /// Input directory containing translation files
+    input_dir: String,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// ARB files to import
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+  },
 }
 
 fn main() -> Result<(), WoofError> {
   let args = Args::parse();
-  let result = collect::collect_and_build_modules(&args.input_dir)?;
-  result.diagnostics.report();
 
-  let out = Path::new(&args.out);
-  generate::generate(out, &result.locales, &result.module)?;
+  match args.command {
+    None => {
+      let input_dir = args.input_dir.expect("input dir is required");
+      let result = collect_and_build_modules_with(&input_dir, &args.build.options())?;
+      result.diagnostics.report();
+
+      let out = Path::new(&args.out);
+      match args.target {
+        Target::Typescript => {
+          generate::generate(
+            out,
+            &result.locales,
+            &result.module,
+            &generate::TypescriptOptions {
+              import_style: args.import_style.into(),
+              mts: args.mts,
+              single_file: args.single_file,
+              lazy: args.lazy,
+              lookup_tables: args.lookup_tables,
+            },
+          )?;
+
+          if args.lookup_tables {
+            report_sizes(&generate::estimate_sizes(&result.module));
+          }
+        }
+        Target::Javascript => generate::javascript::generate(out, &result.locales, &result.module)?,
+        Target::Android => generate::android::generate(
+          out,
+          &result.locales,
+          &result.module,
+          args.separator.as_deref().unwrap_or("_"),
+        )?,
+        Target::Ios => generate::ios::generate(
+          out,
+          &result.locales,
+          &result.module,
+          args.separator.as_deref().unwrap_or("."),
+        )?,
+        Target::Rust => generate::rust::generate(out, &result.locales, &result.module)?,
+        Target::Python => generate::python::generate(out, &result.locales, &result.module)?,
+        Target::Go => generate::go::generate(out, &result.locales, &result.module)?,
+        Target::Kotlin => generate::kotlin::generate(
+          out,
+          &result.locales,
+          &result.module,
+          args.package.as_deref(),
+        )?,
+        Target::Swift => generate::swift::generate(out, &result.locales, &result.module)?,
+      }
+    }
+
+    Some(Command::Export(ExportFormat::Po {
+      out,
+      input_dir,
+      build,
+    })) => {
+      let result = collect_and_build_modules_with(&input_dir, &build.options())?;
+      result.diagnostics.report();
+
+      po::export(Path::new(&out), &result.locales, &result.module)?;
+    }
+
+    Some(Command::Export(ExportFormat::Csv {
+      out,
+      input_dir,
+      build,
+    })) => {
+      let result = collect_and_build_modules_with(&input_dir, &build.options())?;
+      result.diagnostics.report();
+
+      csv::export(Path::new(&out), &result.locales, &result.module)?;
+    }
+
+    Some(Command::Export(ExportFormat::Xliff {
+      source,
+      target,
+      out,
+      input_dir,
+      build,
+    })) => {
+      let result = collect_and_build_modules_with(&input_dir, &build.options())?;
+      result.diagnostics.report();
+
+      let out = out.unwrap_or_else(|| PathBuf::from(format!("{target}.xlf")));
+      xliff::export(&out, &Locale(source), &Locale(target), &result.module)?;
+    }
+
+    Some(Command::Export(ExportFormat::Arb {
+      out,
+      input_dir,
+      build,
+    })) => {
+      let result = collect_and_build_modules_with(&input_dir, &build.options())?;
+      result.diagnostics.report();
+
+      arb::export(Path::new(&out), &result.locales, &result.module)?;
+    }
+
+    Some(Command::Import(ImportFormat::Po {
+      input_dir,
+      files,
+      build,
+    })) => {
+      let (updated, diagnostics) = po::import(Path::new(&input_dir), &files, &build.options())?;
+      diagnostics.report();
+      report_updates(&updated);
+    }
+
+    Some(Command::Import(ImportFormat::Csv {
+      input_dir,
+      file,
+      build,
+    })) => {
+      let (updated, diagnostics) = csv::import(Path::new(&input_dir), &file, &build.options())?;
+      diagnostics.report();
+      report_updates(&updated);
+    }
+
+    Some(Command::Import(ImportFormat::Xliff {
+      input_dir,
+      files,
+      build,
+    })) => {
+      let (updated, diagnostics) = xliff::import(Path::new(&input_dir), &files, &build.options())?;
+      diagnostics.report();
+      report_updates(&updated);
+    }
+
+    Some(Command::Import(ImportFormat::Arb {
+      input_dir,
+      files,
+      build,
+    })) => {
+      let (updated, diagnostics) = arb::import(Path::new(&input_dir), &files, &build.options())?;
+      diagnostics.report();
+      report_updates(&updated);
+    }
+  }
 
   Ok(())
 }
+
+fn report_updates(updated: &[(PathBuf, usize)]) {
+  if updated.is_empty() {
+    eprintln!("No translations changed");
+    return;
+  }
+
+  for (path, changes) in updated {
+    eprintln!(
+      "Updated {} translations in {}",
+      changes.cyan(),
+      path.display().green()
+    );
+  }
+}
+
+fn parse_max_length(value: &str) -> Result<(String, usize), String> {
+  let (path, length) = value
+    .split_once('=')
+    .ok_or_else(|| "expected a key path and length like `buttons=20`".to_string())?;
+
+  let length = length
+    .parse()
+    .map_err(|_| format!("invalid length `{length}`"))?;
+
+  Ok((path.to_string(), length))
+}
+
+fn parse_custom_type(value: &str) -> Result<CustomType, String> {
+  let expected = || "expected a type like `money=Money,formatMoney,./money`".to_string();
+  let (name, rest) = value.split_once('=').ok_or_else(expected)?;
+
+  let [type_name, formatter, module] = rest
+    .split(',')
+    .map(str::trim)
+    .collect::<Vec<_>>()
+    .try_into()
+    .map_err(|_| expected())?;
+
+  if [name, type_name, formatter, module]
+    .iter()
+    .any(|s| s.is_empty())
+  {
+    return Err(expected());
+  }
+
+  Ok(CustomType {
+    name: name.trim().to_string(),
+    type_name: type_name.to_string(),
+    formatter: formatter.to_string(),
+    module: module.to_string(),
+  })
+}
+
+fn report_sizes(estimate: &generate::SizeEstimate) {
+  let saved = estimate.if_chains.saturating_sub(estimate.lookup_tables);
+  let percent = (saved * 100)
+    .checked_div(estimate.if_chains)
+    .unwrap_or_default();
+
+  eprintln!(
+    "Message functions are an estimated {} bytes minified, instead of {} bytes with if-chains ({} smaller)",
+    estimate.lookup_tables.cyan(),
+    estimate.if_chains,
+    format!("{percent}%").green()
+  );
+}
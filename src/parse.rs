@@ -1,16 +1,24 @@
 use crate::{
   collect::ParsedFile,
   context::{Context, Diagnostics, KeyDiagnostic},
-  interpolations::{Interpolation, parse_interpolations},
+  interpolations::{
+    CustomType, Interpolation, InterpolationType, ParsedInterpolation, ParsedInterpolations,
+    ParsedReference, parse_str, parse_str_with_types,
+  },
 };
-use std::collections::{BTreeMap, HashMap};
-use toml::{Table, Value};
+use miette::{LabeledSpan, SourceSpan};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use toml_edit::{InlineTable, Item, TableLike, Value};
 
 use crate::{
   errors::WoofError,
-  sanitize::{escape_translation, sanitize_key},
+  sanitize::{escape_translation, sanitize_key, unescape_translation},
 };
 
+/// The locale used as the source language, e.g. for exports
+pub static DEFAULT_LOCALE: &str = "en";
+
+/// A locale as given by the name of a translation file, e.g. `en` or `pt-BR`
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Locale(pub String);
 
@@ -26,9 +34,12 @@ impl std::fmt::Display for Locale {
   }
 }
 
+/// The key of a message or module
 #[derive(Debug, Clone)]
 pub struct Key {
+  /// The key as written in the translation file
   pub literal: String,
+  /// The key as a valid TypeScript identifier
   pub sanitized: String,
 }
 
@@ -48,7 +59,7 @@ impl std::cmp::Eq for Key {}
 
 impl std::cmp::PartialOrd for Key {
   fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-    Some(self.literal.cmp(&other.literal))
+    Some(self.cmp(other))
   }
 }
 
@@ -67,6 +78,7 @@ impl Key {
   }
 }
 
+/// The translation of a message into one locale, escaped for use in template literals
 #[derive(Debug, Clone)]
 pub struct Translation(pub String);
 
@@ -75,69 +87,467 @@ impl Translation {
     let escaped = escape_translation(literal);
     Self(escaped)
   }
+
+  /// Returns the translation as it was written in the translation file
+  pub fn literal(&self) -> String {
+    unescape_translation(&self.0)
+  }
 }
 
+/// A message with its translations into all locales
 #[derive(Debug, Default)]
 pub struct Message {
   pub translation: BTreeMap<Locale, Translation>,
+  /// Interpolations found in any of the translations, keyed by their name
   pub interpolations: BTreeMap<Key, Interpolation>,
+  /// Comments preceding the message in each locale's file
+  pub comments: BTreeMap<Locale, String>,
+  pub metadata: Metadata,
+  /// Translations with their references to other messages replaced by the referenced text, for
+  /// locales whose translation has references
+  pub resolved: BTreeMap<Locale, Translation>,
+}
+
+/// Keys of an inline table that defines a message with metadata instead of a module
+static METADATA_KEYS: &[&str] = &["text", "description", "maxLength", "deprecated", "tags"];
+
+/// Metadata of a message, given by writing it as an inline table with the translation as `text`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+  /// Explains the message to translators
+  pub description: Option<String>,
+  /// Maximum number of characters of a translation
+  pub max_length: Option<usize>,
+  /// Why the message shouldn't be used anymore, which may be empty
+  pub deprecated: Option<String>,
+  pub tags: Vec<String>,
+}
+
+impl Metadata {
+  /// Takes the fields set in another locale's file. Fields of the default locale take precedence
+  /// over the other locales.
+  fn merge(&mut self, other: Metadata, is_default_locale: bool) {
+    fn merge_field<T>(field: &mut Option<T>, other: Option<T>, overwrite: bool) {
+      if other.is_some() && (overwrite || field.is_none()) {
+        *field = other;
+      }
+    }
+
+    merge_field(&mut self.description, other.description, is_default_locale);
+    merge_field(&mut self.max_length, other.max_length, is_default_locale);
+    merge_field(&mut self.deprecated, other.deprecated, is_default_locale);
+
+    if !other.tags.is_empty() && (is_default_locale || self.tags.is_empty()) {
+      self.tags = other.tags;
+    }
+  }
+}
+
+/// Limits for the length of translations, in addition to the `maxLength` of messages
+#[derive(Debug, Clone, Default)]
+pub struct LengthLimits {
+  /// Maximum lengths of the messages at a key path or within a module, e.g. `checkout.buttons`
+  pub max_lengths: BTreeMap<String, usize>,
+  /// Number of characters each interpolation is counted as
+  pub placeholder_width: usize,
 }
 
+impl LengthLimits {
+  /// The maximum length of a message, taken from its metadata or the most specific key path
+  fn max_length(&self, key_path: &str, message: &Message) -> Option<usize> {
+    if let Some(max_length) = message.metadata.max_length {
+      return Some(max_length);
+    }
+
+    self
+      .max_lengths
+      .iter()
+      .filter(|(path, _)| {
+        key_path
+          .strip_prefix(path.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+      })
+      .max_by_key(|(path, _)| path.len())
+      .map(|(_, max_length)| *max_length)
+  }
+}
+
+/// Options for building modules from translation files
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+  pub limits: LengthLimits,
+  /// Types that can be used for interpolations besides the built-in ones
+  pub custom_types: Vec<CustomType>,
+}
+
+/// Whether an inline table is a message with metadata, i.e. has a string `text` and only metadata
+/// keys. All other tables are modules, so modules that only have keys named like metadata have to
+/// be written as regular tables.
+pub(crate) fn is_message_table(table: &InlineTable) -> bool {
+  table.get("text").is_some_and(Value::is_str)
+    && table.iter().all(|(key, _)| METADATA_KEYS.contains(&key))
+}
+
+/// Reads the metadata of a message table, skipping fields with the wrong type
+fn parse_metadata(table: &InlineTable) -> (Metadata, Option<KeyDiagnostic>) {
+  let mut metadata = Metadata::default();
+  let mut diagnostic = None;
+  let mut invalid = |name: &str, expected: &str| {
+    diagnostic.get_or_insert(KeyDiagnostic::InvalidMetadata {
+      name: name.to_string(),
+      expected: expected.to_string(),
+    });
+  };
+
+  for (name, value) in table.iter() {
+    match name {
+      "description" => match value.as_str() {
+        Some(description) => metadata.description = Some(description.to_string()),
+        None => invalid(name, "a string"),
+      },
+      "maxLength" => match value.as_integer().and_then(|n| usize::try_from(n).ok()) {
+        Some(max_length) => metadata.max_length = Some(max_length),
+        None => invalid(name, "a positive integer"),
+      },
+      "deprecated" => match value {
+        Value::String(reason) => metadata.deprecated = Some(reason.value().clone()),
+        Value::Boolean(deprecated) if *deprecated.value() => {
+          metadata.deprecated = Some(String::new())
+        }
+        Value::Boolean(_) => {}
+        _ => invalid(name, "a string or boolean"),
+      },
+      "tags" => {
+        let tags = value.as_array().and_then(|tags| {
+          tags
+            .iter()
+            .map(|tag| tag.as_str().map(String::from))
+            .collect()
+        });
+
+        match tags {
+          Some(tags) => metadata.tags = tags,
+          None => invalid(name, "an array of strings"),
+        }
+      }
+      _ => {}
+    }
+  }
+
+  (metadata, diagnostic)
+}
+
+/// A piece of a translation, with interpolations resolved to the message's arguments
+#[derive(Debug, Clone)]
+pub enum Part {
+  /// Literal text, still escaped for use in template literals
+  Text(String),
+  /// An argument, with the text used if it's omitted
+  Interpolation {
+    name: Key,
+    default: Option<String>,
+  },
+  Select {
+    name: Key,
+    variants: Vec<Variant>,
+  },
+}
+
+/// A variant of a select expression
+#[derive(Debug, Clone)]
+pub struct Variant {
+  pub key: String,
+  pub default: bool,
+  pub parts: Vec<Part>,
+}
+
+/// Plural categories as returned by `Intl.PluralRules`
+pub static PLURAL_CATEGORIES: &[&str] = &["zero", "one", "two", "few", "many", "other"];
+
 impl Message {
-  /// Creates a template string for the given locale by replacing interpolations
-  /// with JavaScript template literal syntax `${name}`.
-  ///
-  /// The interpolations are replaced in the escaped string, maintaining proper
-  /// offsets as the string length changes during replacement.
-  pub fn template_for_locale(&self, locale: &Locale) -> Option<String> {
-    // Get the translation for this locale
-    let translation = self.translation.get(locale)?;
-    let mut result = translation.0.clone();
+  /// Parses interpolations in a translation of this message, accepting the custom types that
+  /// were inferred for its interpolations
+  pub fn parse_translation(&self, translation: &Translation) -> ParsedInterpolations {
+    parse_str_with_types(&translation.0, &self.custom_types())
+  }
 
-    // Collect all interpolations for this locale and sort by start position
-    let mut interpolations: Vec<(&Key, (usize, usize))> = self
+  /// The custom types of the message's interpolations
+  pub fn custom_types(&self) -> Vec<CustomType> {
+    self
       .interpolations
-      .iter()
-      .filter_map(|(key, interp)| interp.ranges.get(locale).map(|&range| (key, range)))
-      .collect();
+      .values()
+      .filter_map(|interpolation| match &interpolation.type_ {
+        InterpolationType::Custom(custom) => Some(custom.clone()),
+        _ => None,
+      })
+      .collect()
+  }
 
-    // Sort by start position (ascending)
-    interpolations.sort_by_key(|(_, (start, _))| *start);
+  /// Splits the translation for the given locale into literal text and interpolations.
+  ///
+  /// Only interpolations that were registered for this locale are resolved, everything else is
+  /// kept as literal text.
+  pub fn parts_for_locale(&self, locale: &Locale) -> Option<Vec<Part>> {
+    let translation = self
+      .resolved
+      .get(locale)
+      .or_else(|| self.translation.get(locale))?;
+    let parsed = self.parse_translation(translation);
+
+    Some(self.collect_parts(
+      locale,
+      &translation.0,
+      (0, translation.0.len()),
+      &parsed.interpolations,
+    ))
+  }
+
+  fn collect_parts(
+    &self,
+    locale: &Locale,
+    s: &str,
+    (start, end): (usize, usize),
+    interpolations: &[ParsedInterpolation],
+  ) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut cursor = start;
+
+    for interpolation in interpolations {
+      let key = Key::new(&interpolation.name);
+      let registered = self
+        .interpolations
+        .get(&key)
+        .is_some_and(|i| i.ranges.contains_key(locale));
+
+      if !registered {
+        continue;
+      }
 
-    // Replace interpolations from back to front to avoid offset issues
-    // Reverse so we process from end to start
-    interpolations.reverse();
+      push_text(&mut parts, &s[cursor..interpolation.start]);
+      cursor = interpolation.end + 1;
 
-    for (key, (start, end)) in interpolations {
-      let template_var = format!("${{args.{}}}", key.sanitized);
-      result.replace_range(start..=end, &template_var);
+      if interpolation.variants.is_empty() {
+        parts.push(Part::Interpolation {
+          name: key,
+          default: interpolation.default.clone(),
+        });
+        continue;
+      }
+
+      let variants = interpolation
+        .variants
+        .iter()
+        .map(|variant| Variant {
+          key: variant.key.clone(),
+          default: variant.default,
+          parts: self.collect_parts(
+            locale,
+            s,
+            (variant.start, variant.end),
+            &variant.interpolations,
+          ),
+        })
+        .collect();
+
+      parts.push(Part::Select {
+        name: key,
+        variants,
+      });
     }
 
-    // Replace escaped braces {{ with literal braces {
-    // This is safe to do after interpolation replacement since all real
-    // interpolations are now in ${args.name} format
-    result = result.replace("{{", "{");
+    push_text(&mut parts, &s[cursor..end]);
+    parts
+  }
 
+  /// Creates a template string for the given locale by replacing interpolations
+  /// with JavaScript template literal syntax `${name}`.
+  ///
+  /// Select expressions are rendered as nested conditionals. Number selectors match
+  /// numeric variant keys exactly and everything else against the locale's plural category.
+  pub fn template_for_locale(&self, locale: &Locale) -> Option<String> {
+    let parts = self.parts_for_locale(locale)?;
+    let mut result = String::new();
+    self.write_template(&mut result, locale, &parts);
     Some(result)
   }
+
+  fn write_template(&self, out: &mut String, locale: &Locale, parts: &[Part]) {
+    for part in parts {
+      match part {
+        Part::Text(text) => out.push_str(text),
+        Part::Interpolation { name, default } => {
+          let arg = format!("args.{}", name.sanitized);
+          let formatter = match self.interpolations.get(name).map(|i| &i.type_) {
+            Some(InterpolationType::Custom(custom)) => Some(&custom.formatter),
+            _ => None,
+          };
+
+          // Defaults are still escaped like the rest of the translation
+          let value = match (formatter, default) {
+            (None, None) => arg,
+            (None, Some(default)) => format!("{arg} ?? `{default}`"),
+            (Some(formatter), None) => format!("{formatter}({arg}, \"{locale}\")"),
+            (Some(formatter), Some(default)) => {
+              format!("{arg} === undefined ? `{default}` : {formatter}({arg}, \"{locale}\")")
+            }
+          };
+
+          out.push_str(&format!("${{{value}}}"));
+        }
+        Part::Select { name, variants } => {
+          let type_ = self.interpolations.get(name).map(|i| &i.type_);
+          let is_number = type_ == Some(&InterpolationType::Number);
+          let cases = type_.and_then(InterpolationType::cases);
+
+          let arg = format!("args.{}", name.sanitized);
+
+          // Exact numeric matches take precedence over plural categories. Keys that aren't cases
+          // of a boolean or enum can never match, and comparing them wouldn't type check.
+          let mut conditions = variants
+            .iter()
+            .filter(|v| !v.default)
+            .filter(|v| {
+              cases
+                .as_ref()
+                .is_none_or(|cases| cases.contains(&v.key.as_str()))
+            })
+            .map(|v| {
+              // Booleans and numbers are compared with their literals, without quotes
+              let is_boolean = type_ == Some(&InterpolationType::Boolean);
+              if is_boolean || is_number && v.key.parse::<f64>().is_ok() {
+                (0, format!("{arg} === {}", v.key), v)
+              } else if is_number && PLURAL_CATEGORIES.contains(&v.key.as_str()) {
+                let rules = format!("new Intl.PluralRules(\"{locale}\").select({arg})");
+                (1, format!("{rules} === \"{}\"", v.key), v)
+              } else {
+                (0, format!("{arg} === \"{}\"", v.key), v)
+              }
+            })
+            .collect::<Vec<_>>();
+
+          conditions.sort_by_key(|(order, _, _)| *order);
+
+          out.push_str("${");
+          for (_, condition, variant) in conditions {
+            out.push_str(&format!("{condition} ? `"));
+            self.write_template(out, locale, &variant.parts);
+            out.push_str("` : ");
+          }
+
+          out.push('`');
+          if let Some(default) = variants.iter().find(|v| v.default) {
+            self.write_template(out, locale, &default.parts);
+          }
+          out.push_str("`}");
+        }
+      }
+    }
+  }
+}
+
+fn push_text(parts: &mut Vec<Part>, text: &str) {
+  if text.is_empty() {
+    return;
+  }
+
+  // Replace escaped braces {{ with literal braces {
+  parts.push(Part::Text(text.replace("{{", "{")));
 }
 
+/// A module of messages, corresponding to a table in the translation files. The root module
+/// contains all messages, and in namespaced mode a module for each namespace.
 #[derive(Debug, Default)]
 pub struct Module {
   pub messages: BTreeMap<Key, Message>,
   pub modules: BTreeMap<Key, Module>,
+  /// Comments preceding the table in each locale's file
+  pub comments: BTreeMap<Locale, String>,
+}
+
+impl Module {
+  /// Returns all messages in this module and its submodules, together with their key paths. The
+  /// key paths use the literal keys, joined by dots.
+  pub fn flatten(&self) -> Vec<(String, &Message)> {
+    self.flatten_with(".")
+  }
+
+  /// Like [`Module::flatten`], but joins the literal keys with the given separator
+  pub fn flatten_with(&self, separator: &str) -> Vec<(String, &Message)> {
+    self.flatten_by(&|path| path.join(separator))
+  }
+
+  /// Like [`Module::flatten`], but writes the key paths as TOML dotted keys, so keys containing
+  /// dots are quoted. Exports use these key paths, and imports split them with
+  /// [`split_key_path`].
+  pub fn flatten_dotted(&self) -> Vec<(String, &Message)> {
+    self.flatten_by(&|path| {
+      path
+        .iter()
+        .map(|key| toml_edit::Key::new(*key).display_repr().into_owned())
+        .collect::<Vec<_>>()
+        .join(".")
+    })
+  }
+
+  fn flatten_by(&self, join: &dyn Fn(&[&str]) -> String) -> Vec<(String, &Message)> {
+    fn collect<'a>(
+      module: &'a Module,
+      join: &dyn Fn(&[&str]) -> String,
+      path: &mut Vec<&'a str>,
+      out: &mut Vec<(String, &'a Message)>,
+    ) {
+      for (key, message) in &module.messages {
+        path.push(&key.literal);
+        out.push((join(path), message));
+        path.pop();
+      }
+
+      for (key, module) in &module.modules {
+        path.push(&key.literal);
+        collect(module, join, path, out);
+        path.pop();
+      }
+    }
+
+    let mut result = Vec::new();
+    collect(self, join, &mut Vec::new(), &mut result);
+    result
+  }
+}
+
+/// Splits a key path written by [`Module::flatten_dotted`] into its keys
+pub fn split_key_path(key_path: &str) -> Result<Vec<String>, WoofError> {
+  let keys =
+    toml_edit::Key::parse(key_path).map_err(|_| WoofError::InvalidKeyPath(key_path.to_string()))?;
+
+  Ok(keys.iter().map(|key| key.get().to_string()).collect())
 }
 
 pub fn build_namespaced_module(
   namespaces: HashMap<String, HashMap<Locale, ParsedFile>>,
+  options: &BuildOptions,
 ) -> Result<(Module, Diagnostics), WoofError> {
   let mut root_module = Module::default();
   let mut diagnostics = Diagnostics::default();
+  let mut files = Vec::new();
 
   for (namespace, locales) in namespaces {
-    let module = build_root_module(locales, &mut diagnostics, vec![&namespace])?;
+    let (module, paths) = read_root_module(locales, options, &mut diagnostics, vec![&namespace])?;
     let key = crate::parse::Key::new(&namespace);
     root_module.modules.insert(key, module);
+    files.push((namespace, paths));
+  }
+
+  // References can point into other namespaces, so they're resolved once all are read
+  let roots = files
+    .iter()
+    .map(|(namespace, paths)| (vec![namespace.as_str()], paths))
+    .collect::<Vec<_>>();
+  resolve_references(&mut root_module, &roots, &mut diagnostics);
+
+  for (path, paths) in &roots {
+    let module = &root_module.modules[&Key::new(path[0])];
+    check_root_module(module, path, paths, options, &mut diagnostics);
   }
 
   Ok((root_module, diagnostics))
@@ -145,124 +555,753 @@ pub fn build_namespaced_module(
 
 pub fn build_flat_module(
   files: HashMap<Locale, ParsedFile>,
+  options: &BuildOptions,
 ) -> Result<(Module, Diagnostics), WoofError> {
   let mut diagnostics = Diagnostics::default();
-  let root_module = build_root_module(files, &mut diagnostics, vec![])?;
+  let (mut root_module, paths) = read_root_module(files, options, &mut diagnostics, vec![])?;
+  resolve_references(&mut root_module, &[(vec![], &paths)], &mut diagnostics);
+  check_root_module(&root_module, &[], &paths, options, &mut diagnostics);
   Ok((root_module, diagnostics))
 }
 
-fn build_root_module(
+/// Reads the files of all locales into a module, returning it with the path of each locale's file
+fn read_root_module(
   locales: HashMap<Locale, ParsedFile>,
+  options: &BuildOptions,
   diagnostics: &mut Diagnostics,
   path: Vec<&str>,
-) -> Result<Module, WoofError> {
+) -> Result<(Module, HashMap<Locale, String>), WoofError> {
   let mut root_module = Module::default();
+  let paths = locales
+    .iter()
+    .map(|(locale, file)| (locale.clone(), file.normalized_path.clone()))
+    .collect::<HashMap<_, _>>();
 
   for (locale, file) in locales {
-    let Value::Table(table) = file.contents else {
-      unreachable!("root is always a table");
-    };
-
     let mut ctx = Context {
       normalized_file_path: &file.normalized_path,
       locale: &locale,
+      custom_types: &options.custom_types,
       key_path: path.clone(),
       messages: &mut root_module.messages,
       modules: &mut root_module.modules,
       diagnostics,
     };
 
-    build_module(&mut ctx, table)?;
+    for (key, diagnostic) in file.diagnostics {
+      ctx.add_key_diagnostics(&key, diagnostic);
+    }
+
+    build_module(&mut ctx, &file.contents)?;
   }
 
-  Ok(root_module)
+  // Types depend on other locales' files, so they're inferred once all are read
+  infer_interpolation_types(&mut root_module, &path, &options.custom_types, diagnostics);
+  Ok((root_module, paths))
 }
 
-fn build_module(ctx: &mut Context, table: Table) -> Result<(), WoofError> {
-  for (key, value) in table {
-    match value {
-      Value::String(s) => {
-        let translation = Translation::new(&s);
-        let interpolations = parse_interpolations(&translation);
-        if !interpolations.errors.is_empty() {
-          ctx.add_key_diagnostics(
-            &key,
-            KeyDiagnostic::InterpolationErrors {
-              source_code: translation.0,
-              errors: interpolations.errors,
-            },
-          );
-        }
+/// Checks maximum lengths, interpolations and select cases, which depend on all locales' files
+/// and the resolved references
+fn check_root_module(
+  module: &Module,
+  path: &[&str],
+  paths: &HashMap<Locale, String>,
+  options: &BuildOptions,
+  diagnostics: &mut Diagnostics,
+) {
+  check_max_lengths(module, path, paths, &options.limits, diagnostics);
+  check_interpolations(module, path, paths, diagnostics);
+  check_select_cases(module, path, paths, diagnostics);
+}
 
-        let message = ctx.messages.entry(Key::new(&key)).or_default();
-        message
-          .translation
-          .insert(ctx.locale.clone(), Translation::new(&s));
-
-        // We have to collect mismatches instead of adding them immediately because we still hold
-        // a reference to the message
-        // TODO: Smallvec?
-        let mut mismatches = vec![];
-
-        for interpolation in interpolations.interpolations {
-          let entry = message
-            .interpolations
-            .entry(Key::new(&interpolation.name))
-            .or_insert_with(|| Interpolation {
-              type_: interpolation.type_,
-              ranges: HashMap::with_capacity(1),
+/// Why a reference to another message couldn't be resolved
+enum ReferenceError {
+  Unknown,
+  Untranslated,
+  /// The key paths of a cycle back to the message being resolved, joined by arrows
+  Cycle(String),
+  /// A message referenced further down couldn't be resolved, which is reported at that message
+  Broken,
+}
+
+/// Resolves the references of a message in a single locale, collecting the interpolations of all
+/// referenced messages
+struct Resolver<'a> {
+  messages: &'a HashMap<String, &'a Message>,
+  locale: &'a Locale,
+  interpolations: BTreeMap<Key, Interpolation>,
+}
+
+impl<'a> Resolver<'a> {
+  /// Returns the text of the message at the given key path, with its own references resolved.
+  /// The stack holds the key paths being resolved, starting with the referencing message.
+  fn resolve(&mut self, path: &str, stack: &mut Vec<&'a str>) -> Result<String, ReferenceError> {
+    if let Some(index) = stack.iter().position(|p| *p == path) {
+      // Other cycles are reported at the messages that are part of them
+      return Err(if index == 0 {
+        let cycle = stack.iter().copied().chain([path]).collect::<Vec<_>>();
+        ReferenceError::Cycle(cycle.join(" → "))
+      } else {
+        ReferenceError::Broken
+      });
+    }
+
+    let messages = self.messages;
+    let Some((path, message)) = messages.get_key_value(path) else {
+      return Err(ReferenceError::Unknown);
+    };
+
+    let Some(translation) = message.translation.get(self.locale) else {
+      return Err(ReferenceError::Untranslated);
+    };
+
+    for (key, interpolation) in &message.interpolations {
+      self
+        .interpolations
+        .entry(key.clone())
+        .or_insert_with(|| interpolation.clone());
+    }
+
+    stack.push(path);
+    let references = parse_str(&translation.0).references;
+    let text = splice(&translation.0, &references, |reference| {
+      self.resolve(&reference.path, stack)
+    });
+    stack.pop();
+
+    text.map_err(|errors| {
+      errors
+        .into_iter()
+        .find_map(|(_, err)| matches!(err, ReferenceError::Cycle(_)).then_some(err))
+        .unwrap_or(ReferenceError::Broken)
+    })
+  }
+}
+
+/// Replaces the references in a text with the text returned by `resolve`, or returns all
+/// references that couldn't be resolved
+fn splice<'r, E>(
+  text: &str,
+  references: &'r [ParsedReference],
+  mut resolve: impl FnMut(&ParsedReference) -> Result<String, E>,
+) -> Result<String, Vec<(&'r ParsedReference, E)>> {
+  let mut out = String::new();
+  let mut errors = Vec::new();
+  let mut cursor = 0;
+
+  for reference in references {
+    out.push_str(&text[cursor..reference.start]);
+    cursor = reference.end + 1;
+
+    match resolve(reference) {
+      Ok(resolved) => out.push_str(&resolved),
+      Err(err) => errors.push((reference, err)),
+    }
+  }
+
+  out.push_str(&text[cursor..]);
+  if errors.is_empty() {
+    Ok(out)
+  } else {
+    Err(errors)
+  }
+}
+
+/// A translation with its references resolved, and the interpolations of the referenced messages
+struct ResolvedTranslation {
+  locale: Locale,
+  translation: Translation,
+  /// Range of the first reference, which the referenced interpolations point to
+  range: (usize, usize),
+  interpolations: BTreeMap<Key, Interpolation>,
+}
+
+/// Resolves references like `{@common.brand}` to the text of the referenced message in the same
+/// locale, and adds the referenced interpolations to the referencing messages. Key paths start at
+/// the root of the whole tree, so references can point into other namespaces. Unknown messages
+/// and cycles are reported at the reference. `roots` holds the key path and file paths of each
+/// root module, which diagnostics are reported in.
+fn resolve_references(
+  module: &mut Module,
+  roots: &[(Vec<&str>, &HashMap<Locale, String>)],
+  diagnostics: &mut Diagnostics,
+) {
+  let flattened = module.flatten();
+  let messages = flattened
+    .iter()
+    .map(|(path, message)| (path.clone(), *message))
+    .collect::<HashMap<_, _>>();
+
+  let mut resolved = HashMap::<String, Vec<ResolvedTranslation>>::new();
+
+  for (key_path, message) in &flattened {
+    for (locale, translation) in &message.translation {
+      let references = parse_str(&translation.0).references;
+      let Some(first) = references.first() else {
+        continue;
+      };
+
+      let mut resolver = Resolver {
+        messages: &messages,
+        locale,
+        interpolations: BTreeMap::new(),
+      };
+
+      let mut stack = vec![key_path.as_str()];
+      let errors = match splice(&translation.0, &references, |reference| {
+        resolver.resolve(&reference.path, &mut stack)
+      }) {
+        Ok(text) => {
+          resolved
+            .entry(key_path.clone())
+            .or_default()
+            .push(ResolvedTranslation {
+              locale: locale.clone(),
+              translation: Translation(text),
+              range: (first.start, first.end),
+              interpolations: resolver.interpolations,
             });
+          continue;
+        }
+        Err(errors) => errors,
+      };
 
-          if interpolation.type_ != entry.type_ {
-            mismatches.push((interpolation.name, interpolation.type_, entry.clone()));
-            continue;
-          }
+      let file = roots
+        .iter()
+        .find(|(prefix, _)| {
+          key_path
+            .split('.')
+            .take(prefix.len())
+            .eq(prefix.iter().copied())
+        })
+        .and_then(|(_, paths)| paths.get(locale));
+
+      let Some(file) = file else {
+        continue;
+      };
+
+      for (reference, err) in errors {
+        let source_code = translation.0.clone();
+        let span = (reference.start..reference.end + 1).into();
+        let path = reference.path.clone();
+
+        let diagnostic = match err {
+          ReferenceError::Unknown => KeyDiagnostic::UnknownReference {
+            source_code,
+            span,
+            help: format!("There's no message with the key path `{path}`"),
+            path,
+          },
+          ReferenceError::Untranslated => KeyDiagnostic::UnknownReference {
+            source_code,
+            span,
+            help: format!("`{path}` has no translation for {locale}"),
+            path,
+          },
+          ReferenceError::Cycle(cycle) => KeyDiagnostic::ReferenceCycle {
+            source_code,
+            span,
+            cycle,
+          },
+          ReferenceError::Broken => continue,
+        };
+
+        diagnostics.add_key_diagnostics(locale.clone(), file, key_path.clone(), diagnostic);
+      }
+    }
+  }
+
+  add_resolved(module, "", &mut resolved);
+}
 
-          entry
-            .ranges
-            .insert(ctx.locale.clone(), (interpolation.start, interpolation.end));
+fn add_resolved(
+  module: &mut Module,
+  path: &str,
+  resolved: &mut HashMap<String, Vec<ResolvedTranslation>>,
+) {
+  for (key, message) in &mut module.messages {
+    let key_path = if path.is_empty() {
+      key.literal.clone()
+    } else {
+      format!("{path}.{}", key.literal)
+    };
+
+    for resolved in resolved.remove(&key_path).unwrap_or_default() {
+      for (key, referenced) in resolved.interpolations {
+        let interpolation = message.interpolations.entry(key).or_default();
+        if interpolation.type_ == InterpolationType::None {
+          interpolation.type_ = referenced.type_;
         }
 
-        if !mismatches.is_empty() {
-          ctx.add_interpolation_type_mismatches(&key, mismatches);
+        interpolation.required |= referenced.required;
+        interpolation
+          .ranges
+          .entry(resolved.locale.clone())
+          .or_insert(resolved.range);
+      }
+
+      message
+        .resolved
+        .insert(resolved.locale, resolved.translation);
+    }
+  }
+
+  for (key, module) in &mut module.modules {
+    let path = if path.is_empty() {
+      key.literal.clone()
+    } else {
+      format!("{path}.{}", key.literal)
+    };
+
+    add_resolved(module, &path, resolved);
+  }
+}
+
+/// Infers the type of each interpolation from the types declared in all locales. Untyped
+/// interpolations unify with any declared type. If locales declare different types, they're
+/// reported and the default locale's type is used, or the first locale's if it's untyped.
+fn infer_interpolation_types(
+  module: &mut Module,
+  path: &[&str],
+  custom_types: &[CustomType],
+  diagnostics: &mut Diagnostics,
+) {
+  for (key, message) in &mut module.messages {
+    let mut declared = BTreeMap::<String, Vec<(Locale, InterpolationType)>>::new();
+
+    for (locale, translation) in &message.translation {
+      for interpolation in parse_str_with_types(&translation.0, custom_types).flattened() {
+        if interpolation.type_ != InterpolationType::None {
+          declared
+            .entry(interpolation.name.clone())
+            .or_default()
+            .push((locale.clone(), interpolation.type_.clone()));
         }
       }
+    }
+
+    for (name, types) in declared {
+      let Some(interpolation) = message.interpolations.get_mut(&Key::new(&name)) else {
+        continue;
+      };
 
-      Value::Table(table) => {
-        let module = ctx.modules.entry(Key::new(&key)).or_default();
-        let mut key_path = ctx.key_path.clone();
-        key_path.push(&key);
-
-        let mut ctx = Context {
-          locale: ctx.locale,
-          normalized_file_path: ctx.normalized_file_path,
-          key_path,
-          messages: &mut module.messages,
-          modules: &mut module.modules,
-          diagnostics: ctx.diagnostics,
+      let unified = types
+        .iter()
+        .try_fold(InterpolationType::None, |unified, (_, type_)| {
+          unified.unify(type_.clone())
+        });
+
+      interpolation.type_ = match unified {
+        Some(type_) => type_,
+        None => {
+          let type_ = types
+            .iter()
+            .find(|(locale, _)| locale.0 == DEFAULT_LOCALE)
+            .unwrap_or(&types[0])
+            .1
+            .clone();
+
+          let key_path = path
+            .iter()
+            .copied()
+            .chain([key.literal.as_str()])
+            .collect::<Vec<_>>()
+            .join(".");
+
+          diagnostics
+            .interpolation_type_mismatches
+            .entry((key_path, name))
+            .or_default()
+            .extend(types);
+
+          type_
+        }
+      };
+    }
+  }
+
+  for (key, module) in &mut module.modules {
+    let mut path = path.to_vec();
+    path.push(&key.literal);
+    infer_interpolation_types(module, &path, custom_types, diagnostics);
+  }
+}
+
+/// Reports translations that are longer than the maximum length of their message
+fn check_max_lengths(
+  module: &Module,
+  path: &[&str],
+  paths: &HashMap<Locale, String>,
+  limits: &LengthLimits,
+  diagnostics: &mut Diagnostics,
+) {
+  for (key, message) in &module.messages {
+    let key_path = path
+      .iter()
+      .copied()
+      .chain([key.literal.as_str()])
+      .collect::<Vec<_>>()
+      .join(".");
+
+    let Some(max_length) = limits.max_length(&key_path, message) else {
+      continue;
+    };
+
+    for (locale, translation) in &message.translation {
+      let (Some(parts), Some(file)) = (message.parts_for_locale(locale), paths.get(locale)) else {
+        continue;
+      };
+
+      let length = text_length(&parts, limits.placeholder_width);
+      if length > max_length {
+        diagnostics.add_key_diagnostics(
+          locale.clone(),
+          file,
+          key_path.clone(),
+          KeyDiagnostic::TooLong {
+            source_code: translation.0.clone(),
+            span: (0, translation.0.len()).into(),
+            locale: locale.clone(),
+            length,
+            max_length,
+            overflow: length - max_length,
+            help: format!(
+              "Interpolations are counted as {} characters, and select expressions with their longest variant",
+              limits.placeholder_width
+            ),
+          },
+        );
+      }
+    }
+  }
+
+  for (key, module) in &module.modules {
+    let mut path = path.to_vec();
+    path.push(&key.literal);
+    check_max_lengths(module, &path, paths, limits, diagnostics);
+  }
+}
+
+/// Reports translations that use other interpolations than the default locale's translation, or
+/// the first locale's if the message is missing in the default locale
+fn check_interpolations(
+  module: &Module,
+  path: &[&str],
+  paths: &HashMap<Locale, String>,
+  diagnostics: &mut Diagnostics,
+) {
+  for (key, message) in &module.messages {
+    let default_locale = Locale(DEFAULT_LOCALE.to_string());
+    let Some((reference, reference_translation)) = message
+      .translation
+      .get_key_value(&default_locale)
+      .or_else(|| message.translation.first_key_value())
+    else {
+      continue;
+    };
+
+    let expected = message
+      .parse_translation(reference_translation)
+      .flattened()
+      .into_iter()
+      .map(|interpolation| interpolation.name.clone())
+      .collect::<BTreeSet<_>>();
+
+    for (locale, translation) in &message.translation {
+      let Some(file) = paths.get(locale).filter(|_| locale != reference) else {
+        continue;
+      };
+
+      let interpolations = message.parse_translation(translation);
+      let found = interpolations.flattened();
+
+      let mut labels = found
+        .iter()
+        .filter(|interpolation| !expected.contains(&interpolation.name))
+        .map(|interpolation| {
+          LabeledSpan::at(
+            interpolation.start..interpolation.end,
+            format!("not used in {reference}"),
+          )
+        })
+        .collect::<Vec<_>>();
+
+      let missing = expected
+        .iter()
+        .filter(|name| {
+          !found
+            .iter()
+            .any(|interpolation| interpolation.name == **name)
+        })
+        .map(|name| format!("{{{name}}}"))
+        .collect::<Vec<_>>();
+
+      // Missing interpolations are pointed out at the end of the translation
+      if !missing.is_empty() {
+        let end = translation.0.len();
+        let last = translation.0.chars().last().map_or(0, char::len_utf8);
+        labels.push(LabeledSpan::at(
+          end - last..end,
+          format!("missing {}", missing.join(", ")),
+        ));
+      }
+
+      if labels.is_empty() {
+        continue;
+      }
+
+      let key_path = path
+        .iter()
+        .copied()
+        .chain([key.literal.as_str()])
+        .collect::<Vec<_>>()
+        .join(".");
+
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        file,
+        key_path,
+        KeyDiagnostic::InterpolationMismatch {
+          source_code: translation.0.clone(),
+          labels,
+          reference: reference.clone(),
+          help: (!missing.is_empty()).then(|| {
+            format!(
+              "Add {} to the translation, or remove it from {reference}",
+              missing.join(", ")
+            )
+          }),
+        },
+      );
+    }
+  }
+
+  for (key, module) in &module.modules {
+    let mut path = path.to_vec();
+    path.push(&key.literal);
+    check_interpolations(module, &path, paths, diagnostics);
+  }
+}
+
+/// Reports variant keys of selects on booleans and enums that aren't cases of the type, which may
+/// be declared in another locale
+fn check_select_cases(
+  module: &Module,
+  path: &[&str],
+  paths: &HashMap<Locale, String>,
+  diagnostics: &mut Diagnostics,
+) {
+  for (key, message) in &module.messages {
+    for (locale, translation) in &message.translation {
+      let Some(file) = paths.get(locale) else {
+        continue;
+      };
+
+      let interpolations = message.parse_translation(translation);
+      let mut unknown = BTreeMap::<&str, (Vec<&str>, Vec<SourceSpan>)>::new();
+
+      for interpolation in interpolations.flattened() {
+        let Some(cases) = message
+          .interpolations
+          .get(&Key::new(&interpolation.name))
+          .and_then(|i| i.type_.cases())
+        else {
+          continue;
         };
 
-        build_module(&mut ctx, table)?;
+        for variant in &interpolation.variants {
+          if variant.default || cases.contains(&variant.key.as_str()) {
+            continue;
+          }
+
+          // The key is followed by whitespace and the opening brace of the variant's body
+          let key_end = translation.0[..variant.start - 1].trim_end().len();
+          unknown
+            .entry(&interpolation.name)
+            .or_insert_with(|| (cases.clone(), Vec::new()))
+            .1
+            .push((key_end - variant.key.len(), variant.key.len()).into());
+        }
       }
 
-      _ => {
-        ctx.add_key_diagnostics(
-          &key,
-          KeyDiagnostic::UnsupportedValueType {
-            value_type: value.type_str().to_string(),
+      let key_path = path
+        .iter()
+        .copied()
+        .chain([key.literal.as_str()])
+        .collect::<Vec<_>>()
+        .join(".");
+
+      for (name, (cases, keys)) in unknown {
+        diagnostics.add_key_diagnostics(
+          locale.clone(),
+          file,
+          key_path.clone(),
+          KeyDiagnostic::UnknownCases {
+            source_code: translation.0.clone(),
+            keys,
+            name: name.to_string(),
+            help: format!("The cases of {name} are {}", cases.join(", ")),
           },
         );
-        continue;
       }
     }
   }
 
+  for (key, module) in &module.modules {
+    let mut path = path.to_vec();
+    path.push(&key.literal);
+    check_select_cases(module, &path, paths, diagnostics);
+  }
+}
+
+/// Number of characters of the longest text the parts can produce, counting each interpolation
+/// with the given width
+fn text_length(parts: &[Part], placeholder_width: usize) -> usize {
+  parts
+    .iter()
+    .map(|part| match part {
+      Part::Text(text) => unescape_translation(text).chars().count(),
+      Part::Interpolation { .. } => placeholder_width,
+      Part::Select { variants, .. } => variants
+        .iter()
+        .map(|variant| text_length(&variant.parts, placeholder_width))
+        .max()
+        .unwrap_or_default(),
+    })
+    .sum()
+}
+
+/// Adds the translation of a message in the context's locale
+fn add_translation(
+  ctx: &mut Context,
+  table: &dyn TableLike,
+  key: &str,
+  item: &Item,
+  s: &str,
+  metadata: Metadata,
+) {
+  let translation = Translation::new(s);
+  let mut interpolations = parse_str_with_types(&translation.0, ctx.custom_types);
+  if !interpolations.errors.is_empty() {
+    ctx.add_key_diagnostics(
+      key,
+      KeyDiagnostic::InterpolationErrors {
+        source_code: translation.0.clone(),
+        errors: std::mem::take(&mut interpolations.errors),
+      },
+    );
+  }
+
+  let message = ctx.messages.entry(Key::new(key)).or_default();
+  message.translation.insert(ctx.locale.clone(), translation);
+
+  if let Some(comment) = comment_for(table, key, item) {
+    message.comments.insert(ctx.locale.clone(), comment);
+  }
+
+  message
+    .metadata
+    .merge(metadata, ctx.locale.0 == DEFAULT_LOCALE);
+
+  // Types are inferred once all locales are read, see `infer_interpolation_types`
+  for interpolation in interpolations.flattened() {
+    let entry = message
+      .interpolations
+      .entry(Key::new(&interpolation.name))
+      .or_default();
+
+    entry
+      .ranges
+      .insert(ctx.locale.clone(), (interpolation.start, interpolation.end));
+    entry.required |= interpolation.default.is_none();
+  }
+}
+
+fn build_module(ctx: &mut Context, table: &dyn TableLike) -> Result<(), WoofError> {
+  for (key, item) in table.iter() {
+    if let Some(s) = item.as_str() {
+      add_translation(ctx, table, key, item, s, Metadata::default());
+      continue;
+    }
+
+    if let Some(inline) = item.as_inline_table().filter(|t| is_message_table(t)) {
+      let (metadata, diagnostic) = parse_metadata(inline);
+      if let Some(diagnostic) = diagnostic {
+        ctx.add_key_diagnostics(key, diagnostic);
+      }
+
+      let text = inline
+        .get("text")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+      add_translation(ctx, table, key, item, text, metadata);
+      continue;
+    }
+
+    let comment = comment_for(table, key, item);
+    let Some(table) = item.as_table_like() else {
+      ctx.add_key_diagnostics(
+        key,
+        KeyDiagnostic::UnsupportedValueType {
+          value_type: item.type_name().to_string(),
+        },
+      );
+      continue;
+    };
+
+    let module = ctx.modules.entry(Key::new(key)).or_default();
+    if let Some(comment) = comment {
+      module.comments.insert(ctx.locale.clone(), comment);
+    }
+
+    let mut key_path = ctx.key_path.clone();
+    key_path.push(key);
+
+    let mut ctx = Context {
+      locale: ctx.locale,
+      custom_types: ctx.custom_types,
+      normalized_file_path: ctx.normalized_file_path,
+      key_path,
+      messages: &mut module.messages,
+      modules: &mut module.modules,
+      diagnostics: ctx.diagnostics,
+    };
+
+    build_module(&mut ctx, table)?;
+  }
+
   Ok(())
 }
 
+/// Returns the comment directly preceding a key, or the header of a table. Comments separated
+/// from the key by an empty line are ignored.
+fn comment_for(table: &dyn TableLike, key: &str, item: &Item) -> Option<String> {
+  let decor = match item {
+    Item::Table(table) => table.decor(),
+    _ => table.key(key)?.leaf_decor(),
+  };
+
+  let prefix = decor.prefix()?.as_str()?;
+  let mut lines = Vec::new();
+
+  for line in prefix.lines().map(str::trim) {
+    match line.strip_prefix('#') {
+      Some(comment) => lines.push(comment.strip_prefix(' ').unwrap_or(comment)),
+      None => lines.clear(),
+    }
+  }
+
+  if lines.is_empty() {
+    return None;
+  }
+
+  Some(lines.join("\n"))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::interpolations::{CustomType, parse_interpolations};
 
   fn template(input: &str) -> String {
     let translation = Translation::new(input);
@@ -273,9 +1312,9 @@ mod tests {
     message.translation.insert(locale.clone(), translation);
 
     // Add all found interpolations
-    for interp in interpolations.interpolations {
+    for interp in interpolations.flattened() {
       let mut interpolation_obj = Interpolation {
-        type_: interp.type_,
+        type_: interp.type_.clone(),
         ..Default::default()
       };
       interpolation_obj
@@ -436,4 +1475,499 @@ mod tests {
       template("Escape only {{starting double braces}}"),
     ]);
   }
+
+  #[test]
+  fn select_templates() {
+    insta::assert_debug_snapshot!([
+      template("{tier -> free {Free plan} *other {Paid plan}}"),
+      template("{count:number -> 0 {No items} one {One item} *other {{count:number} items}}"),
+      template("Hi {name}, {tier -> free {`free` tier} *other {{tier} tier}}!"),
+      template("{a -> x {{b -> y {nested} *z {{c}}}} *w {{{literal}}"),
+    ]);
+  }
+
+  #[test]
+  fn message_metadata() {
+    let (module, diagnostics) = build(
+      &BuildOptions::default(),
+      &[
+        (
+          "en",
+          r#"
+save = { text = "Save", description = "Saves the form", maxLength = 10, deprecated = "use ui.submit", tags = ["forms"] }
+removed = { text = "Removed", deprecated = true }
+items = { text = "{count:number -> one {One item} *other {{count} items in your cart}}", maxLength = 20 }
+invalid = { text = "Invalid", maxLength = -1, tags = "forms" }
+contact = { text = "Not a message, since it has other keys", email = "Email" }
+"#,
+        ),
+        (
+          "de",
+          r#"
+save = { text = "Speichern und schließen", description = "Ignored, since English sets it", tags = ["ignored"] }
+items = "{count:number -> one {Ein Artikel} *other {{count} Artikel}}"
+"#,
+        ),
+      ],
+    );
+
+    let metadata = module
+      .messages
+      .iter()
+      .map(|(key, message)| (key.literal.as_str(), &message.metadata))
+      .collect::<Vec<_>>();
+    let contact = module.modules[&Key::new("contact")]
+      .messages
+      .keys()
+      .map(|key| key.literal.as_str())
+      .collect::<Vec<_>>();
+
+    insta::assert_debug_snapshot!((metadata, contact, messages(&diagnostics)));
+  }
+
+  #[test]
+  fn message_tables() {
+    let (module, diagnostics) = build(
+      &BuildOptions::default(),
+      &[(
+        "en",
+        r#"
+note = { text = "Shown below", description = "Explains the form" }
+footer = { text = "Footer", links = "Links" }
+card = { text = { title = "Title" }, description = "Card" }
+hint = { text = "Hint", tags = "forms" }
+
+[panel]
+text = "Body"
+description = "Footnote"
+"#,
+      )],
+    );
+
+    let keys = |module: &Module| {
+      module
+        .messages
+        .keys()
+        .map(|key| key.literal.clone())
+        .collect::<Vec<_>>()
+    };
+    let modules = module
+      .modules
+      .iter()
+      .map(|(key, module)| (key.literal.clone(), keys(module)))
+      .collect::<Vec<_>>();
+
+    insta::assert_debug_snapshot!((keys(&module), modules, messages(&diagnostics)));
+  }
+
+  #[test]
+  fn length_limits() {
+    let options = BuildOptions {
+      limits: LengthLimits {
+        max_lengths: BTreeMap::from([
+          ("buttons".to_string(), 8),
+          ("buttons.checkout".to_string(), 12),
+          ("button".to_string(), 1),
+        ]),
+        placeholder_width: 4,
+      },
+      ..Default::default()
+    };
+
+    let (_, diagnostics) = build(
+      &options,
+      &[
+        (
+          "en",
+          r#"
+title = "Not limited, since no path matches it"
+
+[buttons]
+save = "Save"
+checkout = "Checkout"
+cancel = { text = "Cancel", maxLength = 20 }
+greeting = "Hi, {name}!"
+"#,
+        ),
+        (
+          "de",
+          r#"
+[buttons]
+save = "Speichern"
+checkout = "Zur Kasse gehen"
+cancel = "Abbrechen, ohne zu speichern"
+greeting = "Hallo, {name}!"
+"#,
+        ),
+      ],
+    );
+
+    let mut details = diagnostics
+      .file_diagnostics
+      .values()
+      .flat_map(|diagnostics| diagnostics.values().flatten())
+      .filter_map(|diagnostic| match diagnostic {
+        KeyDiagnostic::TooLong {
+          source_code,
+          span,
+          overflow,
+          ..
+        } => Some((source_code.as_str(), *span, *overflow)),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    details.sort_by_key(|(source_code, ..)| *source_code);
+
+    insta::assert_debug_snapshot!((messages(&diagnostics), details));
+  }
+
+  #[test]
+  fn interpolation_mismatches() {
+    let (_, diagnostics) = build(
+      &BuildOptions::default(),
+      &[
+        (
+          "en",
+          r#"
+greeting = "Hello, {name}!"
+items = "{count:number -> one {One item in {list}} *other {{count} items in {list}}}"
+matching = "{a} and {b}"
+"#,
+        ),
+        (
+          "de",
+          r#"
+greeting = "Hallo, {nmae}!"
+items = "{count:number -> one {Ein Artikel} *other {{count} Artikel in {list}}}"
+matching = "{b} und {a}"
+only_german = "Nur {hier}"
+"#,
+        ),
+        (
+          "fr",
+          r#"
+greeting = "Bonjour !"
+only_german = "Seulement {ici}"
+"#,
+        ),
+      ],
+    );
+
+    let mut labels = diagnostics
+      .file_diagnostics
+      .iter()
+      .flat_map(|((locale, _), diagnostics)| {
+        diagnostics.iter().flat_map(move |(key, diagnostics)| {
+          diagnostics
+            .iter()
+            .filter_map(move |diagnostic| match diagnostic {
+              KeyDiagnostic::InterpolationMismatch { labels, help, .. } => Some(format!(
+                "{locale} {key}: {:?} {help:?}",
+                labels
+                  .iter()
+                  .map(|label| (
+                    label.offset(),
+                    label.len(),
+                    label.label().unwrap_or_default()
+                  ))
+                  .collect::<Vec<_>>()
+              )),
+              _ => None,
+            })
+        })
+      })
+      .collect::<Vec<_>>();
+    labels.sort();
+
+    insta::assert_debug_snapshot!((messages(&diagnostics), labels));
+  }
+
+  #[test]
+  fn interpolation_type_unification() {
+    let (module, diagnostics) = build(
+      &BuildOptions::default(),
+      &[
+        (
+          "en",
+          r#"
+items = "{count:number -> one {One item} *other {{count} items}}"
+name = "Hello, {name}"
+"#,
+        ),
+        ("de", r#"items = "{count} Artikel""#),
+        (
+          "fr",
+          r#"
+items = "{count:string} articles"
+name = "Bonjour, {name:string}"
+"#,
+        ),
+      ],
+    );
+
+    let messages = module
+      .messages
+      .iter()
+      .map(|(key, message)| {
+        let types = message
+          .interpolations
+          .iter()
+          .map(|(name, interpolation)| format!("{}: {}", name.literal, interpolation.type_))
+          .collect::<Vec<_>>();
+
+        let templates = message
+          .translation
+          .keys()
+          .filter_map(|locale| {
+            Some(format!(
+              "{locale}: {}",
+              message.template_for_locale(locale)?
+            ))
+          })
+          .collect::<Vec<_>>();
+
+        (key.literal.as_str(), types, templates)
+      })
+      .collect::<Vec<_>>();
+
+    let mut mismatches = diagnostics
+      .interpolation_type_mismatches
+      .into_iter()
+      .map(|((key, name), locales)| {
+        let mut locales = locales
+          .into_iter()
+          .map(|(locale, type_)| format!("{locale}: {type_}"))
+          .collect::<Vec<_>>();
+        locales.sort();
+        (key, name, locales)
+      })
+      .collect::<Vec<_>>();
+    mismatches.sort();
+
+    insta::assert_debug_snapshot!((messages, mismatches));
+  }
+
+  #[test]
+  fn custom_types() {
+    let options = BuildOptions {
+      custom_types: vec![CustomType {
+        name: "money".to_string(),
+        type_name: "Money".to_string(),
+        formatter: "formatMoney".to_string(),
+        module: "./money".to_string(),
+      }],
+      ..Default::default()
+    };
+
+    let (module, diagnostics) = build(
+      &options,
+      &[
+        (
+          "en",
+          r#"
+total = "Total: {total:money}"
+price = "Price: {price:currency}"
+"#,
+        ),
+        ("de", r#"total = "Summe: {total}""#),
+      ],
+    );
+
+    let total = &module.messages[&Key::new("total")];
+    let templates =
+      ["en", "de"].map(|locale| total.template_for_locale(&Locale(locale.to_string())));
+
+    insta::assert_debug_snapshot!((
+      total.interpolations[&Key::new("total")].type_.to_string(),
+      templates,
+      messages(&diagnostics)
+    ));
+  }
+
+  #[test]
+  fn message_references() {
+    let (module, diagnostics) = build(
+      &BuildOptions::default(),
+      &[
+        (
+          "en",
+          r#"
+welcome = "Welcome to {@common.brand}, {name}!"
+plan = "{tier -> free {{@common.brand} Free} *other {Paid}}"
+missing = "See {@common.nope} and {@common.english}"
+a = "A {@b}"
+b = "B {@a}"
+c = "C {@a}"
+
+[common]
+brand = "{product:string} by Acme"
+english = "English"
+"#,
+        ),
+        (
+          "de",
+          r#"
+welcome = "Willkommen bei {@common.brand}, {name}!"
+missing = "Siehe {@common.english}"
+
+[common]
+brand = "{product} von Acme"
+"#,
+        ),
+      ],
+    );
+
+    let welcome = &module.messages[&Key::new("welcome")];
+    let args = welcome
+      .interpolations
+      .iter()
+      .map(|(key, interpolation)| format!("{}: {}", key.literal, interpolation.type_))
+      .collect::<Vec<_>>();
+
+    let templates = [("welcome", "en"), ("welcome", "de"), ("plan", "en")].map(|(key, locale)| {
+      module.messages[&Key::new(key)].template_for_locale(&Locale(locale.to_string()))
+    });
+
+    // References can point into other namespaces
+    let namespaces = HashMap::from([
+      (
+        "app".to_string(),
+        parsed_files(
+          "app/",
+          &[("en", r#"welcome = "Welcome to {@common.brand}""#)],
+        ),
+      ),
+      (
+        "common".to_string(),
+        parsed_files("common/", &[("en", r#"brand = "Acme""#)]),
+      ),
+    ]);
+
+    let (namespaced, _) = build_namespaced_module(namespaces, &BuildOptions::default()).unwrap();
+    let namespaced = namespaced.modules[&Key::new("app")].messages[&Key::new("welcome")]
+      .template_for_locale(&Locale("en".to_string()));
+
+    insta::assert_debug_snapshot!((args, templates, namespaced, messages(&diagnostics)));
+  }
+
+  #[test]
+  fn boolean_and_enum_types() {
+    let (module, diagnostics) = build(
+      &BuildOptions::default(),
+      &[
+        (
+          "en",
+          r#"
+plan = "{tier:enum(free|pro|team) -> free {Free} pro {Pro} *other {Team}}"
+notifications = "{enabled:boolean -> true {On} *false {Off}}"
+"#,
+        ),
+        (
+          "de",
+          r#"
+plan = "{tier -> free {Kostenlos} enterprise {Enterprise} *other {Bezahlt}}"
+notifications = "{enabled -> yes {An} *false {Aus}}"
+"#,
+        ),
+        (
+          "fr",
+          r#"
+plan = "{tier:enum(team|pro|free) -> free {Gratuit} *other {Payant}}"
+notifications = "Notifications : {enabled}"
+"#,
+        ),
+        ("es", r#"plan = "{tier:enum(free|pro)}""#),
+      ],
+    );
+
+    let types = module
+      .messages
+      .iter()
+      .map(|(key, message)| {
+        let type_ = message
+          .interpolations
+          .values()
+          .next()
+          .unwrap()
+          .type_
+          .to_string();
+        let templates =
+          ["en", "de", "fr"].map(|locale| message.template_for_locale(&Locale(locale.to_string())));
+
+        (key.literal.as_str(), type_, templates)
+      })
+      .collect::<Vec<_>>();
+
+    let mut keys = diagnostics
+      .file_diagnostics
+      .values()
+      .flat_map(|diagnostics| diagnostics.values().flatten())
+      .filter_map(|diagnostic| match diagnostic {
+        KeyDiagnostic::UnknownCases {
+          source_code,
+          keys,
+          help,
+          ..
+        } => Some(format!(
+          "{:?}: {help}",
+          keys
+            .iter()
+            .map(|key| &source_code[key.offset()..key.offset() + key.len()])
+            .collect::<Vec<_>>()
+        )),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    keys.sort();
+
+    let mismatches = diagnostics
+      .interpolation_type_mismatches
+      .keys()
+      .collect::<Vec<_>>();
+
+    insta::assert_debug_snapshot!((types, keys, messages(&diagnostics), mismatches));
+  }
+
+  /// Builds a module from the contents of flat translation files
+  fn build(options: &BuildOptions, files: &[(&str, &str)]) -> (Module, Diagnostics) {
+    build_flat_module(parsed_files("", files), options).unwrap()
+  }
+
+  /// Parses the contents of the translation files in a directory, keyed by their locale
+  fn parsed_files(dir: &str, files: &[(&str, &str)]) -> HashMap<Locale, ParsedFile> {
+    files
+      .iter()
+      .map(|(locale, contents)| {
+        let file = ParsedFile {
+          normalized_path: format!("{dir}{locale}.toml"),
+          contents: contents
+            .parse::<toml_edit::DocumentMut>()
+            .unwrap()
+            .as_table()
+            .clone(),
+          diagnostics: Vec::new(),
+        };
+
+        (Locale(locale.to_string()), file)
+      })
+      .collect()
+  }
+
+  /// Sorted messages of all file diagnostics, prefixed with their locale and key path
+  fn messages(diagnostics: &Diagnostics) -> Vec<String> {
+    let mut messages = diagnostics
+      .file_diagnostics
+      .iter()
+      .flat_map(|((locale, _), diagnostics)| {
+        diagnostics.iter().flat_map(move |(key, diagnostics)| {
+          diagnostics
+            .iter()
+            .map(move |diagnostic| format!("{locale} {key}: {diagnostic}"))
+        })
+      })
+      .collect::<Vec<_>>();
+
+    messages.sort();
+    messages
+  }
 }
@@ -0,0 +1,438 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::context::{Diagnostics, KeyDiagnostic};
+use crate::errors::WoofError;
+use crate::interpolations::parse_str_with_types;
+use crate::parse::{BuildOptions, DEFAULT_LOCALE, Locale, Message, Module};
+use crate::update::Updater;
+
+static VERSION: &str = env!("CARGO_PKG_VERSION");
+static TEMPLATE_FILE: &str = "messages.pot";
+
+/// Writes one PO file per locale and a POT template into the given directory.
+///
+/// Every message becomes an entry with its key path as `msgctxt` and the default locale's
+/// translation as `msgid`. Descriptions, or comments from the default locale's file, are written
+/// as extracted comments (`#.`) together with the other metadata, which PO editors show as notes
+/// for translators.
+pub fn export(dir: &Path, locales: &[Locale], module: &Module) -> Result<(), WoofError> {
+  if dir.is_file() {
+    return Err(WoofError::OutputFileExists(
+      dir.to_string_lossy().to_string(),
+    ));
+  }
+
+  fs::create_dir_all(dir)?;
+
+  let messages = module.flatten_dotted();
+  let default_locale = Locale(DEFAULT_LOCALE.to_string());
+
+  fs::write(
+    dir.join(TEMPLATE_FILE),
+    write_po(None, &default_locale, &messages),
+  )?;
+
+  for locale in locales {
+    fs::write(
+      dir.join(format!("{locale}.po")),
+      write_po(Some(locale), &default_locale, &messages),
+    )?;
+  }
+
+  Ok(())
+}
+
+/// Imports translated PO files into the TOML files of the input directory. The locale is taken
+/// from the `Language` header, or the file name if it's missing.
+///
+/// Only entries with a `msgctxt` and a non-empty `msgstr` that aren't marked as fuzzy are
+/// imported, and entries whose interpolations don't parse are reported as diagnostics instead.
+/// Returns the updated files and the number of changed translations in each.
+pub fn import(
+  input_dir: &Path,
+  files: &[impl AsRef<Path>],
+  options: &BuildOptions,
+) -> Result<(Vec<(PathBuf, usize)>, Diagnostics), WoofError> {
+  let mut updater = Updater::new(input_dir)?;
+  let mut diagnostics = Diagnostics::default();
+
+  for file in files {
+    let file = file.as_ref();
+    let filename = file.display().to_string();
+    let contents = fs::read_to_string(file)?;
+    let entries = parse_po(&contents)
+      .map_err(|(line, reason)| WoofError::InvalidPoFile(filename.clone(), line, reason))?;
+
+    let language = entries
+      .iter()
+      .find(|e| e.context.is_none() && e.id.is_empty())
+      .and_then(|header| {
+        header
+          .translation
+          .lines()
+          .find_map(|line| line.strip_prefix("Language:"))
+          .map(|language| language.trim().to_string())
+      })
+      .filter(|language| !language.is_empty());
+
+    let Some(locale) = language.or_else(|| {
+      file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+    }) else {
+      return Err(WoofError::InvalidPoFile(
+        filename,
+        0,
+        "Unknown locale".to_string(),
+      ));
+    };
+
+    let locale = Locale(locale);
+
+    for entry in entries {
+      let Some(context) = &entry.context else {
+        continue;
+      };
+
+      if entry.fuzzy || entry.translation.is_empty() {
+        continue;
+      }
+
+      let parsed = parse_str_with_types(&entry.translation, &options.custom_types);
+      if !parsed.errors.is_empty() {
+        diagnostics.add_key_diagnostics(
+          locale.clone(),
+          &filename,
+          context.clone(),
+          KeyDiagnostic::InterpolationErrors {
+            source_code: entry.translation.clone(),
+            errors: parsed.errors,
+          },
+        );
+
+        continue;
+      }
+
+      updater.set_dotted(&locale, context, &entry.translation)?;
+    }
+  }
+
+  Ok((updater.save()?, diagnostics))
+}
+
+fn write_po(
+  locale: Option<&Locale>,
+  default_locale: &Locale,
+  messages: &[(String, &Message)],
+) -> String {
+  let mut out = String::new();
+
+  let _ = writeln!(out, "# Code generated by woof v{VERSION}.");
+  write_string(&mut out, "msgid", "");
+
+  let mut header = String::new();
+  if let Some(locale) = locale {
+    let _ = writeln!(header, "Language: {locale}");
+  }
+
+  header.push_str("MIME-Version: 1.0\n");
+  header.push_str("Content-Type: text/plain; charset=UTF-8\n");
+  header.push_str("Content-Transfer-Encoding: 8bit\n");
+  let _ = writeln!(header, "X-Generator: woof {VERSION}");
+  write_string(&mut out, "msgstr", &header);
+
+  for (key_path, message) in messages {
+    out.push('\n');
+
+    let comment = message.metadata.description.as_ref().or_else(|| {
+      message
+        .comments
+        .get(default_locale)
+        .or_else(|| locale.and_then(|locale| message.comments.get(locale)))
+    });
+
+    if let Some(comment) = comment {
+      for line in comment.lines() {
+        let _ = writeln!(out, "#. {line}");
+      }
+    }
+
+    if let Some(max_length) = message.metadata.max_length {
+      let _ = writeln!(out, "#. Max length: {max_length}");
+    }
+
+    if let Some(reason) = &message.metadata.deprecated {
+      let _ = writeln!(out, "#. Deprecated: {reason}");
+    }
+
+    if !message.metadata.tags.is_empty() {
+      let _ = writeln!(out, "#. Tags: {}", message.metadata.tags.join(", "));
+    }
+
+    // The msgid has to be unique and non-empty, so fall back to the key path
+    let id = message
+      .translation
+      .get(default_locale)
+      .map(|t| t.literal())
+      .filter(|id| !id.is_empty())
+      .unwrap_or_else(|| key_path.clone());
+
+    let translation = locale
+      .and_then(|locale| message.translation.get(locale))
+      .map(|t| t.literal())
+      .unwrap_or_default();
+
+    write_string(&mut out, "msgctxt", key_path);
+    write_string(&mut out, "msgid", &id);
+    write_string(&mut out, "msgstr", &translation);
+  }
+
+  out
+}
+
+/// Writes a keyword and its quoted string, splitting multiline strings into one line per string
+fn write_string(out: &mut String, keyword: &str, s: &str) {
+  if !s.contains('\n') || s.trim_end_matches('\n').is_empty() {
+    let _ = writeln!(out, "{keyword} \"{}\"", escape(s));
+    return;
+  }
+
+  let _ = writeln!(out, "{keyword} \"\"");
+  for line in s.split_inclusive('\n') {
+    let _ = writeln!(out, "\"{}\"", escape(line));
+  }
+}
+
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '\\' => result.push_str("\\\\"),
+      '"' => result.push_str("\\\""),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      _ => result.push(c),
+    }
+  }
+
+  result
+}
+
+#[derive(Debug, Default)]
+struct PoEntry {
+  context: Option<String>,
+  id: String,
+  translation: String,
+  fuzzy: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+  Context,
+  Id,
+  Translation,
+  /// Plural forms aren't supported, so their strings are skipped
+  Ignored,
+}
+
+/// Parses the entries of a PO file. Errors contain the line number and the reason.
+fn parse_po(s: &str) -> Result<Vec<PoEntry>, (usize, String)> {
+  let mut entries = Vec::new();
+  let mut entry = PoEntry::default();
+  let mut field = None;
+  let mut has_content = false;
+
+  for (index, line) in s.lines().enumerate() {
+    let line_number = index + 1;
+    let line = line.trim();
+
+    if line.is_empty() {
+      continue;
+    }
+
+    if let Some(comment) = line.strip_prefix('#') {
+      // Comments start a new entry
+      if has_content {
+        entries.push(std::mem::take(&mut entry));
+        has_content = false;
+        field = None;
+      }
+
+      if let Some(flags) = comment.strip_prefix(',') {
+        entry.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+      }
+
+      continue;
+    }
+
+    if line.starts_with('"') {
+      let Some(field) = field else {
+        return Err((line_number, "String without a keyword".to_string()));
+      };
+
+      let value = unquote(line).ok_or((line_number, "Invalid string".to_string()))?;
+      match field {
+        Field::Context => entry.context.get_or_insert_default().push_str(&value),
+        Field::Id => entry.id.push_str(&value),
+        Field::Translation => entry.translation.push_str(&value),
+        Field::Ignored => {}
+      }
+
+      continue;
+    }
+
+    let (keyword, rest) = line
+      .split_once(char::is_whitespace)
+      .ok_or((line_number, format!("Expected a string after {line}")))?;
+
+    let value = unquote(rest.trim()).ok_or((line_number, "Invalid string".to_string()))?;
+
+    let next_field = match keyword {
+      "msgctxt" => Field::Context,
+      "msgid" => Field::Id,
+      "msgstr" => Field::Translation,
+      "msgid_plural" => Field::Ignored,
+      keyword if keyword.starts_with("msgstr[") => Field::Ignored,
+      keyword => return Err((line_number, format!("Unknown keyword {keyword}"))),
+    };
+
+    // A context or id after a translation starts a new entry
+    if has_content
+      && matches!(next_field, Field::Context | Field::Id)
+      && matches!(field, Some(Field::Translation | Field::Ignored))
+    {
+      entries.push(std::mem::take(&mut entry));
+    }
+
+    match next_field {
+      Field::Context => entry.context = Some(value),
+      Field::Id => entry.id = value,
+      Field::Translation => entry.translation = value,
+      Field::Ignored => {}
+    }
+
+    field = Some(next_field);
+    has_content = true;
+  }
+
+  if has_content {
+    entries.push(entry);
+  }
+
+  Ok(entries)
+}
+
+fn unquote(s: &str) -> Option<String> {
+  let s = s.strip_prefix('"')?.strip_suffix('"')?;
+  let mut result = String::with_capacity(s.len());
+  let mut chars = s.chars();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+
+    match chars.next()? {
+      'n' => result.push('\n'),
+      'r' => result.push('\r'),
+      't' => result.push('\t'),
+      c => result.push(c),
+    }
+  }
+
+  Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+
+  fn export_locale(locale: Option<&str>) -> String {
+    let module = module(&[
+      (
+        "en",
+        r#"
+# Shown on the dashboard
+# Keep it short
+greeting = 'Hello "{name}"!'
+multiline = "First\nSecond"
+
+[nav]
+home = "Home"
+"#,
+      ),
+      (
+        "de",
+        r#"
+# Shown on the dashboard
+# Keep it short
+greeting = "Hallo `{name}`!"
+untranslated = "Nur auf Deutsch"
+
+[nav]
+home = "Startseite"
+"#,
+      ),
+    ]);
+    let messages = module.flatten();
+
+    let locale = locale.map(|l| Locale(l.to_string()));
+    write_po(locale.as_ref(), &Locale("en".to_string()), &messages)
+  }
+
+  #[test]
+  fn export_po() {
+    insta::assert_snapshot!(export_locale(Some("de")));
+  }
+
+  #[test]
+  fn export_pot() {
+    insta::assert_snapshot!(export_locale(None));
+  }
+
+  #[test]
+  fn parse_exported_po() {
+    insta::assert_debug_snapshot!(parse_po(&export_locale(Some("de"))));
+  }
+
+  #[test]
+  fn parse_po_edge_cases() {
+    insta::assert_debug_snapshot!(parse_po(
+      r#"
+# translator comment
+#, fuzzy, c-format
+msgctxt "fuzzy"
+msgid "Fuzzy"
+msgstr "Unscharf"
+
+msgctxt "plural"
+msgid "One"
+msgid_plural "Many"
+msgstr[0] "Eins"
+msgstr[1] "Viele"
+msgctxt "no.blank.line"
+msgid "Tab\there"
+msgstr ""
+"Multi\n"
+"line"
+
+#~ msgctxt "obsolete"
+#~ msgid "Old"
+#~ msgstr "Alt"
+"#
+    ));
+  }
+
+  #[test]
+  fn parse_po_errors() {
+    assert!(parse_po("msgid \"unclosed").is_err());
+    assert!(parse_po("\"no keyword\"").is_err());
+    assert!(parse_po("msgfoo \"unknown\"").is_err());
+  }
+}
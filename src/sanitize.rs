@@ -159,6 +159,99 @@ fn is_reserved_keyword(word: &str) -> bool {
   keywords.contains(word)
 }
 
+/// Makes a sanitized key usable as an identifier in another language, by appending an underscore
+/// if it's one of the given reserved words
+pub fn escape_keyword(sanitized: &str, keywords: &[&str]) -> String {
+  if keywords.contains(&sanitized) {
+    format!("{sanitized}_")
+  } else {
+    sanitized.to_string()
+  }
+}
+
+/// Converts a key into an exported Go identifier, e.g. `hello-world` or `hello_world` into
+/// `HelloWorld`.
+///
+/// Rules:
+/// - Words are split at all characters that aren't letters or numbers
+/// - The first letter of every word is uppercased
+/// - Names that don't start with an uppercase letter get an `X` prepended
+///
+/// Go keywords are all lowercase, so they can't collide with exported names.
+pub fn go_exported_name(key: &str) -> String {
+  let mut name = key
+    .split(|c: char| !c.is_alphanumeric())
+    .map(|word| {
+      let mut chars = word.chars();
+      chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default()
+    })
+    .collect::<String>();
+
+  // Digits and letters without case can't make a name exported
+  if !name.starts_with(char::is_uppercase) {
+    name.insert(0, 'X');
+  }
+
+  name
+}
+
+/// Converts a key into a Go package name, e.g. `Hello-World` into `helloworld`.
+///
+/// Rules:
+/// - Only letters and numbers are kept, and letters are lowercased
+/// - Names that don't start with a letter get an `x` prepended
+/// - Go keywords and names with a special meaning get an underscore appended
+pub fn go_package_name(key: &str) -> String {
+  static KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+    // Packages named main are commands, and these directories are ignored or restricted by the
+    // go tool
+    "main",
+    "internal",
+    "testdata",
+    "vendor",
+  ];
+
+  let mut name = key
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect::<String>();
+
+  if !name.starts_with(char::is_alphabetic) {
+    name.insert(0, 'x');
+  }
+
+  escape_keyword(&name, KEYWORDS)
+}
+
 /// Escapes a translation string for use in JavaScript template literals.
 ///
 /// This function escapes special characters that have meaning in template literals
@@ -193,6 +286,26 @@ pub fn escape_translation(s: &str) -> String {
   result
 }
 
+/// Reverses [`escape_translation`], returning the original translation string.
+pub fn unescape_translation(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+  let mut chars = s.chars();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+
+    // Every backslash starts an escape sequence, so the next character is always literal
+    if let Some(next) = chars.next() {
+      result.push(next);
+    }
+  }
+
+  result
+}
+
 /// Checks whether a string matches `[a-zA-Z_][a-zA-Z0-9_]*`
 pub fn is_valid_identifier(s: &str) -> bool {
   let mut chars = s.chars();
@@ -370,4 +483,37 @@ mod tests {
       escape_translation("Single } characters are fine"),
     ]);
   }
+
+  #[test]
+  fn go_names() {
+    insta::assert_debug_snapshot!([
+      go_exported_name("hello"),
+      go_exported_name("hello_world"),
+      go_exported_name("hello-world"),
+      go_exported_name("helloWorld"),
+      go_exported_name("123hello"),
+      go_exported_name("type"),
+      go_exported_name("测试"),
+      go_exported_name("émoji🎉test"),
+      go_package_name("Hello-World"),
+      go_package_name("type"),
+      go_package_name("main"),
+      go_package_name("123hello"),
+      go_package_name("_internal"),
+    ]);
+  }
+
+  #[test]
+  fn unescape_roundtrip() {
+    for input in [
+      "hello world",
+      "Use `${var}` or {name}",
+      "Path: C:\\Users\\{username}",
+      "\\`${}\\`",
+      "$$$${multiple}",
+      "`${start}{middle:type}${end}`",
+    ] {
+      assert_eq!(unescape_translation(&escape_translation(input)), input);
+    }
+  }
 }
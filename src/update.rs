@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use crate::collect::{FileMode, detect_file_mode};
+use crate::errors::WoofError;
+use crate::parse::{Locale, is_message_table, split_key_path};
+
+/// Writes translations back into the TOML files of an input directory, preserving their
+/// formatting and comments
+pub struct Updater<'a> {
+  input_dir: &'a Path,
+  mode: FileMode,
+  documents: BTreeMap<PathBuf, (DocumentMut, usize)>,
+}
+
+impl<'a> Updater<'a> {
+  /// Creates an updater for the translation files in the given directory
+  pub fn new(input_dir: &'a Path) -> Result<Self, WoofError> {
+    if !input_dir.is_dir() {
+      return Err(WoofError::InvalidInputDirectory(
+        input_dir.display().to_string(),
+      ));
+    }
+
+    Ok(Self {
+      input_dir,
+      mode: detect_file_mode(input_dir)?,
+      documents: BTreeMap::new(),
+    })
+  }
+
+  /// Sets the translation at the given key path, which starts with the namespace in namespaced
+  /// mode. Returns whether the translation changed.
+  pub fn set(
+    &mut self,
+    locale: &Locale,
+    key_path: &[&str],
+    value: &str,
+  ) -> Result<bool, WoofError> {
+    let (path, key_path) = match self.mode {
+      FileMode::Flat => (self.input_dir.join(format!("{locale}.toml")), key_path),
+      FileMode::Namespaced => {
+        let [namespace, key_path @ ..] = key_path else {
+          return Err(WoofError::InvalidKeyPath(key_path.join(".")));
+        };
+
+        let path = self.input_dir.join(format!("{namespace}.{locale}.toml"));
+        (path, key_path)
+      }
+    };
+
+    let Some((last, parents)) = key_path.split_last() else {
+      return Err(WoofError::InvalidKeyPath(key_path.join(".")));
+    };
+
+    if !self.documents.contains_key(&path) {
+      if path.with_extension("ftl").exists() {
+        return Err(WoofError::ReadOnlyFile(
+          path.with_extension("ftl").display().to_string(),
+        ));
+      }
+
+      let document = if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        contents
+          .parse::<DocumentMut>()
+          .map_err(|err| WoofError::Toml(path.display().to_string(), err))?
+      } else {
+        DocumentMut::new()
+      };
+
+      self.documents.insert(path.clone(), (document, 0));
+    }
+
+    let (document, changes) = self
+      .documents
+      .get_mut(&path)
+      .expect("document was just inserted");
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+
+    for key in parents {
+      let item = table.entry(key).or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+      });
+
+      table = item
+        .as_table_like_mut()
+        .ok_or_else(|| WoofError::InvalidKeyPath(key_path.join(".")))?;
+    }
+
+    match table.get_mut(last) {
+      Some(Item::Value(existing @ Value::String(_))) => {
+        if existing.as_str() == Some(value) {
+          return Ok(false);
+        }
+
+        let decor = existing.decor().clone();
+        *existing = Value::from(value);
+        *existing.decor_mut() = decor;
+      }
+      // Messages with metadata keep their metadata, only the text is replaced
+      Some(Item::Value(Value::InlineTable(message))) if is_message_table(message) => {
+        let existing = message.get_mut("text").expect("message tables have a text");
+        if existing.as_str() == Some(value) {
+          return Ok(false);
+        }
+
+        let decor = existing.decor().clone();
+        *existing = Value::from(value);
+        *existing.decor_mut() = decor;
+      }
+      Some(_) => return Err(WoofError::InvalidKeyPath(key_path.join("."))),
+      None => {
+        table.insert(last, Item::Value(Value::from(value)));
+      }
+    }
+
+    *changes += 1;
+    Ok(true)
+  }
+
+  /// Like [`Updater::set`], but takes a key path written by
+  /// [`Module::flatten_dotted`](crate::parse::Module::flatten_dotted)
+  pub fn set_dotted(
+    &mut self,
+    locale: &Locale,
+    key_path: &str,
+    value: &str,
+  ) -> Result<bool, WoofError> {
+    let key_path = split_key_path(key_path)?;
+    let key_path = key_path.iter().map(String::as_str).collect::<Vec<_>>();
+    self.set(locale, &key_path, value)
+  }
+
+  /// Writes all changed files, returning their paths and the number of changed translations
+  pub fn save(self) -> Result<Vec<(PathBuf, usize)>, WoofError> {
+    let mut result = Vec::new();
+
+    for (path, (document, changes)) in self.documents {
+      if changes == 0 {
+        continue;
+      }
+
+      fs::write(&path, document.to_string())?;
+      result.push((path, changes));
+    }
+
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn locale(locale: &str) -> Locale {
+    Locale(locale.to_string())
+  }
+
+  #[test]
+  fn preserves_formatting() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("de.toml");
+    fs::write(
+      &path,
+      r#"# Greetings
+greeting   =   "Hallo"  # shown on the start page
+
+[nav]
+home = "Start"
+"#,
+    )
+    .unwrap();
+
+    let mut updater = Updater::new(dir.path()).unwrap();
+    assert!(updater.set(&locale("de"), &["greeting"], "Servus").unwrap());
+    assert!(
+      !updater
+        .set(&locale("de"), &["nav", "home"], "Start")
+        .unwrap()
+    );
+    assert!(
+      updater
+        .set(&locale("de"), &["nav", "about"], "Über uns")
+        .unwrap()
+    );
+    assert_eq!(updater.save().unwrap(), [(path.clone(), 2)]);
+
+    insta::assert_snapshot!(fs::read_to_string(&path).unwrap(), @r#"
+    # Greetings
+    greeting   =   "Servus"  # shown on the start page
+
+    [nav]
+    home = "Start"
+    about = "Über uns"
+    "#);
+  }
+
+  #[test]
+  fn keeps_message_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("de.toml");
+    fs::write(
+      &path,
+      r#"greeting = { text = "Hallo", description = "Shown on the start page", tags = ["home"] }
+"#,
+    )
+    .unwrap();
+
+    let mut updater = Updater::new(dir.path()).unwrap();
+    assert!(updater.set(&locale("de"), &["greeting"], "Servus").unwrap());
+    updater.save().unwrap();
+
+    insta::assert_snapshot!(fs::read_to_string(&path).unwrap(), @r#"greeting = { text = "Servus", description = "Shown on the start page", tags = ["home"] }"#);
+  }
+
+  #[test]
+  fn namespaced_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("common.en.toml"), "title = \"Title\"\n").unwrap();
+    fs::write(dir.path().join("common.de.toml"), "").unwrap();
+
+    let mut updater = Updater::new(dir.path()).unwrap();
+    assert!(
+      updater
+        .set(&locale("de"), &["common", "title"], "Titel")
+        .unwrap()
+    );
+    assert!(matches!(
+      updater.set(&locale("de"), &["common"], "Titel"),
+      Err(WoofError::InvalidKeyPath(_))
+    ));
+
+    let path = dir.path().join("common.de.toml");
+    assert_eq!(updater.save().unwrap(), [(path.clone(), 1)]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "title = \"Titel\"\n");
+  }
+
+  #[test]
+  fn dotted_key_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("de.toml");
+    fs::write(&path, "[nav]\n\"v1.0\" = \"Version 1\"\n").unwrap();
+
+    let mut updater = Updater::new(dir.path()).unwrap();
+    assert!(
+      updater
+        .set_dotted(&locale("de"), r#"nav."v1.0""#, "Version 1.0")
+        .unwrap()
+    );
+    assert!(matches!(
+      updater.set_dotted(&locale("de"), "nav.", "Version"),
+      Err(WoofError::InvalidKeyPath(_))
+    ));
+    updater.save().unwrap();
+
+    assert_eq!(
+      fs::read_to_string(&path).unwrap(),
+      "[nav]\n\"v1.0\" = \"Version 1.0\"\n"
+    );
+  }
+
+  #[test]
+  fn fluent_files_are_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("en.toml"), "title = \"Title\"\n").unwrap();
+    fs::write(dir.path().join("de.ftl"), "title = Titel\n").unwrap();
+
+    let mut updater = Updater::new(dir.path()).unwrap();
+    assert!(matches!(
+      updater.set(&locale("de"), &["title"], "Überschrift"),
+      Err(WoofError::ReadOnlyFile(_))
+    ));
+    assert!(updater.save().unwrap().is_empty());
+    assert_eq!(
+      fs::read_to_string(dir.path().join("de.ftl")).unwrap(),
+      "title = Titel\n"
+    );
+  }
+}
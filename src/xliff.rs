@@ -0,0 +1,667 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+
+use crate::context::{Diagnostics, KeyDiagnostic};
+use crate::errors::WoofError;
+use crate::interpolations::{
+  CustomType, ParsedInterpolation, ParsedReference, parse_str_with_types,
+};
+use crate::parse::{BuildOptions, Locale, Module};
+use crate::update::Updater;
+
+static XLIFF_NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:2.0";
+
+/// A piece of a translation as shown to translators
+#[derive(Debug, PartialEq)]
+enum Piece {
+  /// Translatable text, with brace escapes resolved
+  Text(String),
+  /// Interpolations and the syntax of select expressions, which translators can't change
+  Placeholder(String),
+}
+
+/// Writes an XLIFF 2.0 file for translating from the source to the target locale.
+///
+/// Every message becomes a unit with its key path as id. Interpolations and the syntax around
+/// select variants are encoded as `<ph>` placeholders, so only the text can be translated.
+pub fn export(
+  path: &Path,
+  source: &Locale,
+  target: &Locale,
+  module: &Module,
+) -> Result<(), WoofError> {
+  if path.is_dir() {
+    return Err(WoofError::OutputFileExists(
+      path.to_string_lossy().to_string(),
+    ));
+  }
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  fs::write(path, write_xliff(source, target, module))?;
+  Ok(())
+}
+
+fn write_xliff(source: &Locale, target: &Locale, module: &Module) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+  let _ = writeln!(
+    out,
+    r#"<xliff xmlns="{XLIFF_NAMESPACE}" version="2.0" srcLang="{}" trgLang="{}">"#,
+    escape(&source.0),
+    escape(&target.0),
+  );
+  let _ = writeln!(out, r#"  <file id="messages">"#);
+
+  for (key_path, message) in module.flatten_dotted() {
+    let Some(source_translation) = message.translation.get(source) else {
+      continue;
+    };
+
+    let custom_types = message.custom_types();
+    let source_pieces = pieces(&source_translation.literal(), &custom_types);
+    let target_pieces = message
+      .translation
+      .get(target)
+      .map(|t| pieces(&t.literal(), &custom_types));
+
+    let _ = writeln!(out, r#"    <unit id="{}">"#, escape(&key_path));
+
+    let mut notes = Vec::new();
+    let metadata = &message.metadata;
+    if let Some(comment) = metadata
+      .description
+      .as_ref()
+      .or_else(|| message.comments.get(source))
+    {
+      notes.push(format!("<note>{}</note>", escape(comment)));
+    }
+
+    if let Some(max_length) = metadata.max_length {
+      notes.push(format!(r#"<note category="maxLength">{max_length}</note>"#));
+    }
+
+    if let Some(reason) = &metadata.deprecated {
+      notes.push(format!(
+        r#"<note category="deprecated">{}</note>"#,
+        escape(reason)
+      ));
+    }
+
+    if !metadata.tags.is_empty() {
+      notes.push(format!(
+        r#"<note category="tags">{}</note>"#,
+        escape(&metadata.tags.join(", "))
+      ));
+    }
+
+    if !notes.is_empty() {
+      let _ = writeln!(out, "      <notes>");
+      for note in notes {
+        let _ = writeln!(out, "        {note}");
+      }
+
+      let _ = writeln!(out, "      </notes>");
+    }
+
+    let state = if target_pieces.is_some() {
+      "translated"
+    } else {
+      "initial"
+    };
+
+    let _ = writeln!(out, r#"      <segment state="{state}">"#);
+
+    let ids = (1..=source_pieces.len())
+      .map(|id| id.to_string())
+      .collect::<Vec<_>>();
+
+    let _ = writeln!(
+      out,
+      "        <source>{}</source>",
+      write_pieces(&source_pieces, &ids)
+    );
+
+    if let Some(target_pieces) = target_pieces {
+      let ids = target_ids(&source_pieces, &target_pieces);
+      let _ = writeln!(
+        out,
+        "        <target>{}</target>",
+        write_pieces(&target_pieces, &ids)
+      );
+    }
+
+    let _ = writeln!(out, "      </segment>");
+    let _ = writeln!(out, "    </unit>");
+  }
+
+  let _ = writeln!(out, "  </file>");
+  let _ = writeln!(out, "</xliff>");
+
+  out
+}
+
+/// Imports the targets of translated XLIFF 2.0 files into the TOML files of the input directory.
+///
+/// Placeholders in each target have to match the ones in its source. Units with missing or unknown
+/// placeholders are skipped and reported as diagnostics. Returns the updated files and the number
+/// of changed translations in each.
+pub fn import(
+  input_dir: &Path,
+  files: &[impl AsRef<Path>],
+  options: &BuildOptions,
+) -> Result<(Vec<(PathBuf, usize)>, Diagnostics), WoofError> {
+  let mut updater = Updater::new(input_dir)?;
+  let mut diagnostics = Diagnostics::default();
+
+  for file in files {
+    let file = file.as_ref();
+    let filename = file.display().to_string();
+    let contents = fs::read_to_string(file)?;
+
+    let (locale, units) = read_xliff(
+      &filename,
+      &contents,
+      &options.custom_types,
+      &mut diagnostics,
+    )?;
+    for (key_path, translation) in units {
+      updater.set_dotted(&locale, &key_path, &translation)?;
+    }
+  }
+
+  Ok((updater.save()?, diagnostics))
+}
+
+/// Reads the translated units of an XLIFF file, returning the target locale and the key path and
+/// translation of every valid unit. Invalid units are added to the diagnostics.
+fn read_xliff(
+  filename: &str,
+  contents: &str,
+  custom_types: &[CustomType],
+  diagnostics: &mut Diagnostics,
+) -> Result<(Locale, Vec<(String, String)>), WoofError> {
+  let document = Document::parse(contents)
+    .map_err(|err| WoofError::InvalidXliffFile(filename.to_string(), err.to_string()))?;
+
+  let root = document.root_element();
+  let Some(target) = root.attribute("trgLang") else {
+    return Err(WoofError::InvalidXliffFile(
+      filename.to_string(),
+      "Missing trgLang attribute".to_string(),
+    ));
+  };
+
+  let locale = Locale(target.to_string());
+  let mut units = Vec::new();
+
+  for unit in root.descendants().filter(|n| n.has_tag_name("unit")) {
+    let Some(key_path) = unit.attribute("id") else {
+      continue;
+    };
+
+    let mut placeholders = HashMap::new();
+    let mut translation = String::new();
+    let mut has_target = false;
+    let mut missing = Vec::new();
+    let mut unknown = Vec::new();
+
+    for segment in unit.children().filter(|n| n.has_tag_name("segment")) {
+      let Some(source) = segment.children().find(|n| n.has_tag_name("source")) else {
+        continue;
+      };
+
+      for ph in source.descendants().filter(|n| n.has_tag_name("ph")) {
+        if let (Some(id), Some(equiv)) = (ph.attribute("id"), ph.attribute("equiv")) {
+          placeholders.insert(id, equiv);
+        }
+      }
+
+      let Some(target) = segment.children().find(|n| n.has_tag_name("target")) else {
+        continue;
+      };
+
+      has_target = true;
+      let mut used = Vec::new();
+      read_target(
+        target,
+        &placeholders,
+        &mut translation,
+        &mut used,
+        &mut unknown,
+      );
+
+      // Every placeholder of the source has to be used at least once
+      for ph in source.descendants().filter(|n| n.has_tag_name("ph")) {
+        if let Some(equiv) = ph.attribute("equiv")
+          && !used.contains(&equiv)
+        {
+          missing.push(equiv.to_string());
+        }
+      }
+    }
+
+    if !has_target || translation.is_empty() {
+      continue;
+    }
+
+    if !missing.is_empty() || !unknown.is_empty() {
+      let help = if missing.is_empty() {
+        "Only use placeholders from the source text".to_string()
+      } else {
+        format!("Missing placeholders: {}", missing.join(", "))
+      };
+
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        filename,
+        key_path.to_string(),
+        KeyDiagnostic::PlaceholderMismatch {
+          source_code: translation,
+          unknown: unknown.into_iter().map(Into::into).collect(),
+          help,
+        },
+      );
+
+      continue;
+    }
+
+    let parsed = parse_str_with_types(&translation, custom_types);
+    if !parsed.errors.is_empty() {
+      diagnostics.add_key_diagnostics(
+        locale.clone(),
+        filename,
+        key_path.to_string(),
+        KeyDiagnostic::InterpolationErrors {
+          source_code: translation,
+          errors: parsed.errors,
+        },
+      );
+
+      continue;
+    }
+
+    units.push((key_path.to_string(), translation));
+  }
+
+  Ok((locale, units))
+}
+
+/// Reads the text of a target, replacing placeholders with their original syntax. Resolved
+/// placeholders are recorded, unknown ones are kept as XML and their ranges are recorded.
+fn read_target<'a>(
+  node: Node,
+  placeholders: &HashMap<&str, &'a str>,
+  out: &mut String,
+  used: &mut Vec<&'a str>,
+  unknown: &mut Vec<(usize, usize)>,
+) {
+  for child in node.children() {
+    if let Some(text) = child.text()
+      && child.is_text()
+    {
+      out.push_str(&text.replace('{', "{{"));
+      continue;
+    }
+
+    if !child.has_tag_name("ph") {
+      // Other inline elements like <mrk> can wrap text, so we keep their contents
+      read_target(child, placeholders, out, used, unknown);
+      continue;
+    }
+
+    let id = child.attribute("id").unwrap_or_default();
+    let equiv = child.attribute("equiv");
+
+    // Translators might duplicate a placeholder, which gives it a new id but keeps its text
+    let resolved = placeholders.get(id).copied().or_else(|| {
+      placeholders
+        .values()
+        .find(|v| equiv.is_some_and(|equiv| equiv == **v))
+        .copied()
+    });
+
+    match resolved {
+      Some(text) => {
+        out.push_str(text);
+        used.push(text);
+      }
+      None => {
+        let start = out.len();
+        let _ = write!(out, r#"<ph id="{id}"/>"#);
+        unknown.push((start, out.len() - start));
+      }
+    }
+  }
+}
+
+/// Splits a translation into text and placeholders. Interpolations, the syntax of selects and
+/// references to other messages become placeholders.
+fn pieces(literal: &str, custom_types: &[CustomType]) -> Vec<Piece> {
+  let parsed = parse_str_with_types(literal, custom_types);
+  let mut result = Vec::new();
+  push_pieces(
+    literal,
+    (0, literal.len()),
+    &parsed.interpolations,
+    &parsed.references,
+    &mut result,
+  );
+
+  result
+}
+
+fn push_pieces(
+  s: &str,
+  (start, end): (usize, usize),
+  interpolations: &[ParsedInterpolation],
+  references: &[ParsedReference],
+  out: &mut Vec<Piece>,
+) {
+  let mut cursor = start;
+
+  for interpolation in interpolations {
+    push_text(out, s, (cursor, interpolation.start), references);
+    cursor = interpolation.end + 1;
+
+    if interpolation.variants.is_empty() {
+      out.push(Piece::Placeholder(
+        s[interpolation.start..cursor].to_string(),
+      ));
+      continue;
+    }
+
+    // The syntax between variant bodies becomes placeholders, e.g. `} *other {`
+    let mut code_start = interpolation.start;
+    for variant in &interpolation.variants {
+      out.push(Piece::Placeholder(s[code_start..variant.start].to_string()));
+      push_pieces(
+        s,
+        (variant.start, variant.end),
+        &variant.interpolations,
+        references,
+        out,
+      );
+      code_start = variant.end;
+    }
+
+    out.push(Piece::Placeholder(s[code_start..cursor].to_string()));
+  }
+
+  push_text(out, s, (cursor, end), references);
+}
+
+/// Pushes the text between interpolations, with the references in it as placeholders
+fn push_text(
+  out: &mut Vec<Piece>,
+  s: &str,
+  (start, end): (usize, usize),
+  references: &[ParsedReference],
+) {
+  let mut cursor = start;
+
+  for reference in references
+    .iter()
+    .filter(|r| r.start >= start && r.end < end)
+  {
+    push_unescaped(out, &s[cursor..reference.start]);
+    cursor = reference.end + 1;
+    out.push(Piece::Placeholder(s[reference.start..cursor].to_string()));
+  }
+
+  push_unescaped(out, &s[cursor..end]);
+}
+
+fn push_unescaped(out: &mut Vec<Piece>, text: &str) {
+  if !text.is_empty() {
+    out.push(Piece::Text(text.replace("{{", "{")));
+  }
+}
+
+/// Assigns ids to the pieces of a target, reusing the ids of matching source placeholders
+fn target_ids(source: &[Piece], target: &[Piece]) -> Vec<String> {
+  let mut used = vec![false; source.len()];
+  let mut extra = 0;
+
+  target
+    .iter()
+    .map(|piece| {
+      let Piece::Placeholder(text) = piece else {
+        return String::new();
+      };
+
+      let matching = source
+        .iter()
+        .enumerate()
+        .position(|(index, p)| !used[index] && matches!(p, Piece::Placeholder(t) if t == text));
+
+      match matching {
+        Some(index) => {
+          used[index] = true;
+          (index + 1).to_string()
+        }
+        None => {
+          extra += 1;
+          format!("t{extra}")
+        }
+      }
+    })
+    .collect()
+}
+
+fn write_pieces(pieces: &[Piece], ids: &[String]) -> String {
+  let mut out = String::new();
+
+  for (piece, id) in pieces.iter().zip(ids) {
+    match piece {
+      Piece::Text(text) => out.push_str(&escape(text)),
+      Piece::Placeholder(text) => {
+        let text = escape(text);
+        let _ = write!(out, r#"<ph id="{id}" equiv="{text}" disp="{text}"/>"#);
+      }
+    }
+  }
+
+  out
+}
+
+fn escape(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '&' => result.push_str("&amp;"),
+      '<' => result.push_str("&lt;"),
+      '>' => result.push_str("&gt;"),
+      '"' => result.push_str("&quot;"),
+      '\'' => result.push_str("&apos;"),
+      _ => result.push(c),
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::generate::tests::module;
+  use std::collections::BTreeMap;
+
+  fn export() -> String {
+    let module = module(&[
+      (
+        "en",
+        r#"
+# Shown on the dashboard
+greeting = "Hello {name}!"
+items = "{count:number -> 0 {No items} *other {{count} items in {{braces}}"
+
+[nav]
+home = "Home & <away>"
+"#,
+      ),
+      ("de", r#"greeting = "{name}, hallo!""#),
+    ]);
+
+    write_xliff(
+      &Locale("en".to_string()),
+      &Locale("de".to_string()),
+      &module,
+    )
+  }
+
+  #[test]
+  fn export_xliff() {
+    insta::assert_snapshot!(export());
+  }
+
+  #[test]
+  fn select_pieces() {
+    insta::assert_debug_snapshot!(pieces(
+      "{count:number -> 0 {No {{items yet} one {One of {total}} *other {{count} items}}",
+      &[]
+    ));
+  }
+
+  #[test]
+  fn import_xliff() {
+    let xliff = export()
+      .replace(
+        "<target><ph id=\"2\" equiv=\"{name}\" disp=\"{name}\"/>, hallo!</target>",
+        "<target>Hallo <ph id=\"2\" equiv=\"{name}\" disp=\"{name}\"/> und {name}!</target>",
+      )
+      .replace(
+        "<source>Home &amp; &lt;away&gt;</source>",
+        "<source>Home &amp; &lt;away&gt;</source><target>Start</target>",
+      );
+
+    let mut diagnostics = Diagnostics::default();
+    let result = read_xliff("de.xlf", &xliff, &[], &mut diagnostics);
+
+    assert!(diagnostics.is_empty());
+    insta::assert_debug_snapshot!(result);
+  }
+
+  #[test]
+  fn references_round_trip() {
+    let de = "Willkommen bei {@brand}, {{name}! \
+      {count:number -> one {Ein {@common.item}} *other {{count} {@common.items}}}";
+
+    let module = module(&[
+      (
+        "en",
+        r#"welcome = "Welcome to {@brand}, {{name}! {count:number -> one {One {@common.item}} *other {{count} {@common.items}}}""#,
+      ),
+      ("de", &format!("welcome = \"{de}\"")),
+    ]);
+
+    let xliff = write_xliff(
+      &Locale("en".to_string()),
+      &Locale("de".to_string()),
+      &module,
+    );
+    assert!(xliff.contains(r#"<ph id="2" equiv="{@brand}" disp="{@brand}"/>"#));
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, units) = read_xliff("de.xlf", &xliff, &[], &mut diagnostics).unwrap();
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(units, [("welcome".to_string(), de.to_string())]);
+  }
+
+  #[test]
+  fn import_placeholder_mismatches() {
+    let xliff = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="en" trgLang="de">
+  <file id="messages">
+    <unit id="missing">
+      <segment>
+        <source>Hello <ph id="1" equiv="{name}" disp="{name}"/></source>
+        <target>Hallo</target>
+      </segment>
+    </unit>
+    <unit id="unknown">
+      <segment>
+        <source>Hello <ph id="1" equiv="{name}" disp="{name}"/></source>
+        <target>Hallo <ph id="1" equiv="{name}" disp="{name}"/><ph id="7"/></target>
+      </segment>
+    </unit>
+    <unit id="duplicated">
+      <segment>
+        <source>Hello <ph id="1" equiv="{name}" disp="{name}"/></source>
+        <target><ph id="1" equiv="{name}"/> <ph id="2" equiv="{name}"/></target>
+      </segment>
+    </unit>
+  </file>
+</xliff>"#;
+
+    let mut diagnostics = Diagnostics::default();
+    let result = read_xliff("de.xlf", xliff, &[], &mut diagnostics);
+
+    // Sort the diagnostics for a stable snapshot
+    let diagnostics = diagnostics
+      .file_diagnostics
+      .into_iter()
+      .map(|(file, keys)| (file, keys.into_iter().collect::<BTreeMap<_, _>>()))
+      .collect::<BTreeMap<_, _>>();
+
+    insta::assert_debug_snapshot!((result, diagnostics));
+  }
+
+  #[test]
+  fn import_custom_types_xliff() {
+    let xliff = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="en" trgLang="de">
+  <file id="messages">
+    <unit id="total">
+      <segment>
+        <source>Total: <ph id="1" equiv="{total:money}" disp="{total:money}"/></source>
+        <target>Summe: <ph id="1" equiv="{total:money}" disp="{total:money}"/></target>
+      </segment>
+    </unit>
+  </file>
+</xliff>"#;
+
+    let money = CustomType {
+      name: "money".to_string(),
+      type_name: "Money".to_string(),
+      formatter: "formatMoney".to_string(),
+      module: "./money".to_string(),
+    };
+
+    let mut diagnostics = Diagnostics::default();
+    let (_, units) = read_xliff("de.xlf", xliff, &[money], &mut diagnostics).unwrap();
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+      units,
+      [("total".to_string(), "Summe: {total:money}".to_string())]
+    );
+
+    // Without the custom type, `money` is an unknown type
+    let (_, units) = read_xliff("de.xlf", xliff, &[], &mut diagnostics).unwrap();
+
+    assert!(units.is_empty());
+    assert_eq!(diagnostics.file_diagnostics.len(), 1);
+  }
+
+  #[test]
+  fn import_invalid_xliff() {
+    let mut diagnostics = Diagnostics::default();
+    assert!(matches!(
+      read_xliff("de.xlf", "<xliff>", &[], &mut diagnostics),
+      Err(WoofError::InvalidXliffFile(..))
+    ));
+    assert!(matches!(
+      read_xliff("de.xlf", "<xliff></xliff>", &[], &mut diagnostics),
+      Err(WoofError::InvalidXliffFile(..))
+    ));
+  }
+}
@@ -0,0 +1,21 @@
+## Global messages
+title = Willkommen in unserer Anwendung
+greeting = Hallo, { $name }!
+
+login = Anmelden
+    .title = Melde dich bei deinem Konto an
+    .placeholder = Deine E-Mail-Adresse
+
+emails = { $count ->
+    [0] Du hast keine neuen E-Mails
+    [one] Du hast eine neue E-Mail
+   *[other] Du hast { $count } neue E-Mails
+}
+
+plan = { $tier ->
+    [free] Du nutzt den kostenlosen Tarif
+   *[other] Du nutzt den { $tier }-Tarif
+}
+
+-brand = Woof
+about = Über { -brand }
@@ -0,0 +1,21 @@
+## Global messages
+title = Welcome to Our Application
+greeting = Hello, { $name }!
+
+login = Log in
+    .title = Log into your account
+    .placeholder = Your email address
+
+emails = { $count ->
+    [0] You have no new emails
+    [one] You have one new email
+   *[other] You have { $count } new emails
+}
+
+plan = { $tier ->
+    [free] You are on the free plan
+   *[other] You are on the { $tier } plan
+}
+
+-brand = Woof
+about = About { -brand }